@@ -0,0 +1,98 @@
+//! Stand-in for the snx-rs service in `-m command` mode, for testing and demos without VPN access.
+//!
+//! Usage: `snx-rs-mock [--listen ADDR] [--connect SCRIPT] [--disconnect SCRIPT] [--status SCRIPT]
+//! [--challenge SCRIPT] [--idp ADDR]`
+//!
+//! A script is a comma separated list of behaviors consumed one per request:
+//! `accept`, `reject:<message>`, `delay:<secs>`, `drop`, `connect-after:<secs>`, `challenge:<prompt>`,
+//! `sso:<url>`.
+//!
+//! `--idp` also serves a fake identity provider whose login page redirects back to the GUI, use
+//! `--connect sso:http://ADDR/login` to send the GUI there.
+
+use log::{error, LevelFilter};
+use std::process::ExitCode;
+
+use snx_rs_gui::client::DEFAULT_SERVER_ADDRESS;
+use snx_rs_gui::mock::{parse_script, MockIdentityProvider, MockServer};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dioxus_logger::DioxusLogger::new(LevelFilter::Debug)
+        .use_format("[{LEVEL}] {PATH} - {ARGS}")
+        .build()
+        .expect("Failed to initialize logger");
+
+    let mut listen = DEFAULT_SERVER_ADDRESS.to_string();
+    let mut idp = None;
+    let mut connect = Vec::new();
+    let mut disconnect = Vec::new();
+    let mut status = Vec::new();
+    let mut challenge = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(v) => v,
+            None => {
+                error!("Missing value for {}", arg);
+                return ExitCode::FAILURE;
+            }
+        };
+        let script = match arg.as_str() {
+            "--listen" => {
+                listen = value;
+                continue;
+            }
+            "--idp" => {
+                idp = Some(value);
+                continue;
+            }
+            "--connect" => &mut connect,
+            "--disconnect" => &mut disconnect,
+            "--status" => &mut status,
+            "--challenge" => &mut challenge,
+            other => {
+                error!("Unknown argument {}", other);
+                return ExitCode::FAILURE;
+            }
+        };
+        match parse_script(&value) {
+            Ok(s) => *script = s,
+            Err(e) => {
+                error!("Invalid script for {}: {}", arg, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(address) = idp {
+        let spawned = MockIdentityProvider::bind(&address, "mock-sso-token")
+            .await
+            .and_then(|idp| idp.spawn());
+        if let Err(e) = spawned {
+            error!("Cannot serve identity provider on {}: {}", address, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let server = match MockServer::bind(&listen).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Cannot listen on {}: {}", listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = server
+        .on_connect(connect)
+        .on_disconnect(disconnect)
+        .on_status(status)
+        .on_challenge(challenge)
+        .run()
+        .await;
+    if let Err(e) = result {
+        error!("Mock server failed: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE64;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+
+use crate::config::write_atomically;
+use crate::model::TunnelParams;
+
+/// CA certificates expiring within this many days are warned about.
+pub const EXPIRY_WARNING_DAYS: i64 = 30;
+
+const PEM_BEGIN: &[u8] = b"-----BEGIN";
+const PEM_LINE_LENGTH: usize = 64;
+
+/// What the settings show about a CA certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    Valid,
+    /// Expires within `EXPIRY_WARNING_DAYS`, with the days left.
+    ExpiresSoon(i64),
+    Expired,
+    NotYetValid,
+}
+
+impl CertificateInfo {
+    pub fn expiry(&self, now: DateTime<Utc>) -> Expiry {
+        if now < self.not_before {
+            Expiry::NotYetValid
+        } else if now >= self.not_after {
+            Expiry::Expired
+        } else if self.not_after - now <= Duration::days(EXPIRY_WARNING_DAYS) {
+            Expiry::ExpiresSoon((self.not_after - now).num_days())
+        } else {
+            Expiry::Valid
+        }
+    }
+}
+
+impl fmt::Display for CertificateInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}, issued by {}, valid until {}",
+            self.subject,
+            self.issuer,
+            self.not_after.format("%Y-%m-%d")
+        )
+    }
+}
+
+fn invalid(message: impl fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn to_utc(time: ASN1Time) -> Result<DateTime<Utc>, Error> {
+    DateTime::from_timestamp(time.timestamp(), 0)
+        .ok_or_else(|| invalid("certificate time out of range"))
+}
+
+/// Reads subject, issuer and validity of a DER encoded X.509 certificate.
+pub fn parse_der(der: &[u8]) -> Result<CertificateInfo, Error> {
+    let (_, certificate) = X509Certificate::from_der(der).map_err(invalid)?;
+    let validity = certificate.validity();
+    Ok(CertificateInfo {
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        not_before: to_utc(validity.not_before)?,
+        not_after: to_utc(validity.not_after)?,
+    })
+}
+
+/// DER encoding of every certificate of a PEM bundle, or of the single certificate of a DER file.
+fn certificate_ders(data: &[u8]) -> Result<Vec<CertificateDer<'static>>, Error> {
+    if !data.windows(PEM_BEGIN.len()).any(|w| w == PEM_BEGIN) {
+        return Ok(vec![CertificateDer::from(data.to_vec())]);
+    }
+    let certificates = CertificateDer::pem_slice_iter(data)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    match certificates.is_empty() {
+        true => Err(invalid("no certificate found")),
+        false => Ok(certificates),
+    }
+}
+
+/// Reads every certificate of a PEM bundle, or the single certificate of a DER file.
+pub fn parse_certificates(data: &[u8]) -> Result<Vec<CertificateInfo>, Error> {
+    certificate_ders(data)?
+        .iter()
+        .map(|der| parse_der(der))
+        .collect()
+}
+
+pub fn read_certificates(path: &Path) -> Result<Vec<CertificateInfo>, Error> {
+    parse_certificates(&std::fs::read(path)?)
+}
+
+/// Certificates of the CA files read so far, read again when a file's size or modification
+/// time changes, so the settings can be checked on every render.
+#[derive(Debug, Default)]
+pub struct CertificateCache {
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    version: (SystemTime, u64),
+    certificates: Result<Vec<CertificateInfo>, (ErrorKind, String)>,
+}
+
+impl CertificateCache {
+    /// `read_certificates` of `path`, from the cache while the file is unchanged.
+    pub fn read(&mut self, path: &Path) -> Result<Vec<CertificateInfo>, Error> {
+        let version = match std::fs::metadata(path).and_then(|m| Ok((m.modified()?, m.len()))) {
+            Ok(version) => version,
+            Err(e) => {
+                self.files.remove(path);
+                return Err(e);
+            }
+        };
+        let cached = match self.files.get(path) {
+            Some(cached) if cached.version == version => cached,
+            _ => {
+                let certificates = read_certificates(path).map_err(|e| (e.kind(), e.to_string()));
+                self.files
+                    .entry(path.to_path_buf())
+                    .insert_entry(CachedFile {
+                        version,
+                        certificates,
+                    })
+                    .into_mut()
+            }
+        };
+        cached
+            .certificates
+            .clone()
+            .map_err(|(kind, message)| Error::new(kind, message.clone()))
+    }
+}
+
+fn to_pem(der: &[u8]) -> String {
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    let mut base64 = BASE64.encode(der);
+    while !base64.is_empty() {
+        let rest = base64.split_off(base64.len().min(PEM_LINE_LENGTH));
+        pem.push_str(&base64);
+        pem.push('\n');
+        base64 = rest;
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+/// One PEM bundle of the certificates in `paths`, which may be PEM or DER files.
+pub fn ca_bundle(paths: &[PathBuf]) -> Result<String, Error> {
+    let mut bundle = String::new();
+    for path in paths {
+        let certificates = std::fs::read(path)
+            .and_then(|data| certificate_ders(&data))
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        for der in certificates {
+            bundle.push_str(&to_pem(&der));
+        }
+    }
+    Ok(bundle)
+}
+
+/// The params to send: the service takes a single CA file, so several are written to `bundle`
+/// first and sent as that one.
+pub fn bundle_ca_certs(mut params: TunnelParams, bundle: &Path) -> Result<TunnelParams, Error> {
+    if params.ca_cert.len() > 1 {
+        write_atomically(bundle, ca_bundle(&params.ca_cert)?.as_bytes())?;
+        params.ca_cert = vec![bundle.to_path_buf()];
+    }
+    Ok(params)
+}
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+/// Environment variable overriding the snx-rs service address.
+pub const SERVICE_ADDRESS_ENV: &str = "SNX_RS_GUI_SERVICE_ADDRESS";
+
+pub const USAGE: &str = "Usage: snx-rs-gui [OPTIONS]
+       snx-rs-gui --connect [--profile <NAME>] [OPTIONS]
+       snx-rs-gui --disconnect [OPTIONS]
+       snx-rs-gui --status [--json] [OPTIONS]
+       snx-rs-gui --import <FILE> [--profile <NAME>]
+       snx-rs-gui --export <FILE> [--profile <NAME>]
+
+Without --connect, --disconnect, --status, --import or --export the GUI is started.
+
+Options:
+  --connect                      Connect with the last used or the given profile and wait for the tunnel
+  --profile <NAME>               Profile to connect with or to export, name of the imported profile
+  --disconnect                   Disconnect the tunnel
+  --status                       Print the connection status
+  --json                         Print the status as JSON
+  --import <FILE>                Add a profile with the settings of an snx-rs config file
+  --export <FILE>                Write the last used or the given profile as an snx-rs config file
+  --service-address <HOST:PORT>  Address of the snx-rs service in command mode
+                                 (env: SNX_RS_GUI_SERVICE_ADDRESS, default: 127.0.0.1:7779)
+  -h, --help                     Print this help
+
+The password of --connect is read from the secret service when the profile saves it,
+otherwise from SNX_RS_GUI_PASSWORD.";
+
+/// Command run without starting the GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Connect,
+    Disconnect,
+    Status,
+    /// Add a profile from the snx-rs config file in `CliArgs::file`.
+    Import,
+    /// Write a profile to the snx-rs config file in `CliArgs::file`.
+    Export,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub service_address: Option<String>,
+    pub help: bool,
+    pub action: Option<Action>,
+    pub profile: Option<String>,
+    pub json: bool,
+    /// snx-rs config file of `Action::Import` and `Action::Export`.
+    pub file: Option<PathBuf>,
+}
+
+impl CliArgs {
+    /// Parses the arguments without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+        let mut cli_args = CliArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let action = match arg.as_str() {
+                "--connect" => Some(Action::Connect),
+                "--disconnect" => Some(Action::Disconnect),
+                "--status" => Some(Action::Status),
+                "--import" => Some(Action::Import),
+                "--export" => Some(Action::Export),
+                _ => None,
+            };
+            if let Some(action) = action {
+                if cli_args.action.is_some_and(|a| a != action) {
+                    return Err("only one of --connect, --disconnect, --status, --import and --export can be given".to_string());
+                }
+                if matches!(action, Action::Import | Action::Export) {
+                    let file = args.next().ok_or(format!("{} requires a file", arg))?;
+                    cli_args.file = Some(PathBuf::from(file));
+                }
+                cli_args.action = Some(action);
+                continue;
+            }
+            match arg.as_str() {
+                "-h" | "--help" => cli_args.help = true,
+                "--json" => cli_args.json = true,
+                "--profile" => {
+                    cli_args.profile = Some(args.next().ok_or("--profile requires a value")?);
+                }
+                "--service-address" => {
+                    cli_args.service_address =
+                        Some(args.next().ok_or("--service-address requires a value")?);
+                }
+                other => {
+                    if let Some(value) = other.strip_prefix("--service-address=") {
+                        cli_args.service_address = Some(value.to_string());
+                    } else if let Some(value) = other.strip_prefix("--profile=") {
+                        cli_args.profile = Some(value.to_string());
+                    } else {
+                        return Err(format!("unknown argument {}", other));
+                    }
+                }
+            }
+        }
+        if cli_args.profile.is_some()
+            && !matches!(
+                cli_args.action,
+                Some(Action::Connect | Action::Import | Action::Export)
+            )
+        {
+            return Err(
+                "--profile can only be used with --connect, --import and --export".to_string(),
+            );
+        }
+        if cli_args.json && cli_args.action != Some(Action::Status) {
+            return Err("--json can only be used with --status".to_string());
+        }
+        Ok(cli_args)
+    }
+
+    /// Service address given on the command line or, failing that, in the environment.
+    pub fn service_address_override(&self) -> Option<String> {
+        self.service_address.clone().or_else(|| {
+            std::env::var(SERVICE_ADDRESS_ENV)
+                .ok()
+                .filter(|address| !address.trim().is_empty())
+        })
+    }
+}
@@ -0,0 +1,334 @@
+use log::{debug, error, info, warn};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::{timeout, timeout_at, Instant};
+
+use crate::model::{TunnelParams, TunnelServiceRequest, TunnelServiceResponse};
+use crate::protocol::Protocol;
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:7779";
+/// Largest UDP payload, requests and responses are single datagrams.
+pub(crate) const MAX_DATAGRAM_SIZE: usize = 65_507;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+/// How long to wait for the service to accept a connection and for the tunnel to come up.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Times a request that is safe to repeat is sent again when the response does not arrive.
+const DEFAULT_RETRIES: u32 = 2;
+
+/// Why a `Connect` or `Disconnect` command did not succeed.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The service could not be reached or its response could not be read.
+    Transport(Error),
+    /// The service refused the command, e.g. because of a wrong password or an unreachable server.
+    Rejected(String),
+    /// The service answered with a response that does not belong to the command.
+    UnexpectedResponse(Box<TunnelServiceResponse>),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // Not sent at all, the service has nothing to do with it.
+            CommandError::Transport(e) if e.kind() == ErrorKind::Unsupported => write!(f, "{}", e),
+            CommandError::Transport(e) => write!(f, "snx-rs service is not responding: {}", e),
+            CommandError::Rejected(message) => write!(f, "{}", message),
+            CommandError::UnexpectedResponse(response) => {
+                write!(f, "unexpected response from snx-rs service: {:?}", response)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for CommandError {
+    fn from(e: Error) -> Self {
+        CommandError::Transport(e)
+    }
+}
+
+/// Response to a `Connect` or `ChallengeCode` request that the service did not refuse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectResponse {
+    Accepted,
+    /// The service needs a challenge code, answer it with `TunnelClient::send_challenge_code`.
+    Challenge(String),
+    /// The service needs an SSO login at this URL, connect again with `TunnelParams::sso_token` set.
+    IdentityProvider(String),
+}
+
+fn command_result(response: TunnelServiceResponse) -> Result<(), CommandError> {
+    match response {
+        TunnelServiceResponse::Ok => Ok(()),
+        TunnelServiceResponse::Error(message) => {
+            error!("Command rejected: {}", message);
+            Err(CommandError::Rejected(message))
+        }
+        other => Err(CommandError::UnexpectedResponse(Box::new(other))),
+    }
+}
+
+fn connect_result(response: TunnelServiceResponse) -> Result<ConnectResponse, CommandError> {
+    match response {
+        TunnelServiceResponse::PasswordPrompt(prompt) => {
+            info!("Service asks for a challenge code");
+            Ok(ConnectResponse::Challenge(prompt))
+        }
+        TunnelServiceResponse::IdentityProvider(url) => {
+            info!("Service asks for an SSO login at {}", url);
+            Ok(ConnectResponse::IdentityProvider(url))
+        }
+        other => command_result(other).map(|_| ConnectResponse::Accepted),
+    }
+}
+
+/// Requests the service can safely receive twice.
+fn is_repeatable(request: &TunnelServiceRequest) -> bool {
+    matches!(
+        request,
+        TunnelServiceRequest::GetStatus | TunnelServiceRequest::Disconnect
+    )
+}
+
+/// Whether `response` can be the service's answer to `request`.
+fn answers(request: &TunnelServiceRequest, response: &TunnelServiceResponse) -> bool {
+    use TunnelServiceResponse as R;
+    match request {
+        // Every reply `status::connection_details` reads, `Ok` stands for a connected tunnel.
+        TunnelServiceRequest::GetStatus => true,
+        TunnelServiceRequest::Connect(_) | TunnelServiceRequest::ChallengeCode(..) => matches!(
+            response,
+            R::Ok | R::Error(_) | R::PasswordPrompt(_) | R::IdentityProvider(_)
+        ),
+        TunnelServiceRequest::Disconnect => matches!(response, R::Ok | R::Error(_)),
+    }
+}
+
+/// Client for the snx-rs service running in `-m command` mode.
+///
+/// Every request is sent from a fresh UDP socket and the service answers
+/// with a single JSON encoded `TunnelServiceResponse` datagram. Lost `GetStatus`
+/// and `Disconnect` requests are sent again, `Connect` and `ChallengeCode` never
+/// are, as the service may be acting on them already. Responses that cannot
+/// answer a command, e.g. late status reports to a request sent from a reused
+/// port, are discarded. `GetStatus` takes any response.
+#[derive(Debug, Clone)]
+pub struct TunnelClient {
+    server_address: SocketAddr,
+    timeout: Duration,
+    retries: u32,
+    protocol: Protocol,
+}
+
+impl Default for TunnelClient {
+    fn default() -> Self {
+        TunnelClient::new(DEFAULT_SERVER_ADDRESS.parse().unwrap())
+    }
+}
+
+/// Checks that `address` is a `host:port` service endpoint, without looking the host up.
+pub fn check_service_address(address: &str) -> Result<(), Error> {
+    let address = address.trim();
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return match addr.port() {
+            0 => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid service address {}: port must not be 0", address),
+            )),
+            _ => Ok(()),
+        };
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok_and(|p| p > 0) => {
+            Ok(())
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid service address {}: expected host:port", address),
+        )),
+    }
+}
+
+/// Checks a `host:port` service endpoint and resolves it to a socket address.
+///
+/// Host names are looked up asynchronously, a slow name server does not block the caller.
+pub async fn resolve_service_address(address: &str) -> Result<SocketAddr, Error> {
+    check_service_address(address)?;
+    let address = address.trim();
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    lookup_host(address).await?.next().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("service address {} does not resolve", address),
+        )
+    })
+}
+
+impl TunnelClient {
+    pub fn new(server_address: SocketAddr) -> Self {
+        TunnelClient {
+            server_address,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            protocol: Protocol::default(),
+        }
+    }
+
+    /// Sets how long to wait for the service when sending and for each response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how often a lost request that is safe to repeat is sent again.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the protocol requests are encoded for, the basic one by default.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn server_address(&self) -> SocketAddr {
+        self.server_address
+    }
+
+    pub async fn get_status(&self) -> Result<TunnelServiceResponse, Error> {
+        info!("Getting status");
+        self.send(&TunnelServiceRequest::GetStatus).await
+    }
+
+    pub async fn connect(&self, params: TunnelParams) -> Result<ConnectResponse, CommandError> {
+        info!("Connecting user to server...");
+        connect_result(self.send(&TunnelServiceRequest::Connect(params)).await?)
+    }
+
+    /// Answers a `ConnectResponse::Challenge`, which may be followed by another challenge.
+    pub async fn send_challenge_code(
+        &self,
+        code: String,
+        params: TunnelParams,
+    ) -> Result<ConnectResponse, CommandError> {
+        info!("Sending challenge code");
+        connect_result(
+            self.send(&TunnelServiceRequest::ChallengeCode(code, params))
+                .await?,
+        )
+    }
+
+    pub async fn disconnect(&self) -> Result<(), CommandError> {
+        info!("Disconnecting user from server...");
+        command_result(self.send(&TunnelServiceRequest::Disconnect).await?)
+    }
+
+    /// Sends a single request to the service and waits for its response.
+    pub async fn send(
+        &self,
+        request: &TunnelServiceRequest,
+    ) -> Result<TunnelServiceResponse, Error> {
+        let payload = self.protocol.encode(request)?;
+        if payload.len() > MAX_DATAGRAM_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("request of {} bytes does not fit a datagram", payload.len()),
+            ));
+        }
+        let socket = self.create_client_socket().await?;
+        let attempts = match is_repeatable(request) {
+            true => self.retries + 1,
+            false => 1,
+        };
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut attempt = 1;
+        loop {
+            match timeout(self.timeout, socket.send(&payload)).await {
+                Ok(sent) => sent?,
+                Err(_) => return Err(Error::new(ErrorKind::TimedOut, "Request not sent")),
+            };
+            match self.receive(&socket, request, &mut buf).await {
+                Err(e) if e.kind() == ErrorKind::TimedOut && attempt < attempts => {
+                    warn!(
+                        "No response, sending again ({} of {})",
+                        attempt + 1,
+                        attempts
+                    );
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits for a response answering `request`, skipping any that cannot.
+    async fn receive(
+        &self,
+        socket: &UdpSocket,
+        request: &TunnelServiceRequest,
+        buf: &mut [u8],
+    ) -> Result<TunnelServiceResponse, Error> {
+        debug!("Handling response");
+        let deadline = Instant::now() + self.timeout;
+        let mut malformed = None;
+        loop {
+            let received = match timeout_at(deadline, socket.recv(buf)).await {
+                Ok(received) => received?,
+                // A malformed response says more about what went wrong than the timeout.
+                Err(_) => {
+                    error!("Response not received");
+                    return Err(malformed.unwrap_or_else(|| {
+                        Error::new(ErrorKind::TimedOut, "Response not received")
+                    }));
+                }
+            };
+            debug!("received data: {}", received);
+            match serde_json::from_slice::<TunnelServiceResponse>(&buf[..received]) {
+                Ok(response) if answers(request, &response) => {
+                    debug!("Response: {:?}", response);
+                    return Ok(response);
+                }
+                Ok(response) => warn!("Discarding stale response {:?}", response),
+                Err(e) => {
+                    error!("Cannot parse response: {}", e);
+                    malformed = Some(Error::new(
+                        ErrorKind::InvalidData,
+                        "cannot parse service response",
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn create_client_socket(&self) -> Result<UdpSocket, Error> {
+        debug!("Creating client socket");
+        let local_ip = match self.server_address.ip() {
+            IpAddr::V4(ip) if ip.is_loopback() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(ip) if ip.is_loopback() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let socket = UdpSocket::bind(SocketAddr::new(local_ip, 0)).await?;
+        debug!("Connecting to address {}", self.server_address);
+        socket.connect(self.server_address).await?;
+        Ok(socket)
+    }
+}
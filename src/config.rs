@@ -0,0 +1,389 @@
+use log::{debug, error, info, warn};
+use serde_json::{Map, Value};
+use std::fs;
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{Error, ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::model::{Profile, TunnelParams, UserConfig};
+
+pub const CONFIG_DIR_NAME: &str = "snx-rs-gui";
+pub const USER_CONF_FILE: &str = "user-config.json";
+/// The CA files of a profile bundled into one, as the service takes a single file.
+pub const CA_BUNDLE_FILE: &str = "ca-bundle.pem";
+/// Where the config was kept before it moved to the XDG config directory, relative to the working directory.
+const LEGACY_CONF_PATH: &str = "user-config.json";
+/// Version of the on-disk `UserConfig` format written by this build.
+pub const CONFIG_VERSION: u32 = 2;
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// `$XDG_CONFIG_HOME/snx-rs-gui`, falling back to `~/.config/snx-rs-gui`.
+pub fn config_dir() -> Result<PathBuf, Error> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                "Neither XDG_CONFIG_HOME nor HOME is set",
+            )
+        })?;
+    Ok(base.join(CONFIG_DIR_NAME))
+}
+
+pub fn config_path() -> Result<PathBuf, Error> {
+    Ok(config_dir()?.join(USER_CONF_FILE))
+}
+
+pub fn ca_bundle_path() -> Result<PathBuf, Error> {
+    Ok(config_dir()?.join(CA_BUNDLE_FILE))
+}
+
+pub fn read_config() -> Option<UserConfig> {
+    let path = match config_path() {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Cannot locate config: {}", e);
+            return None;
+        }
+    };
+    if let Err(e) = migrate_legacy_config(Path::new(LEGACY_CONF_PATH), &path) {
+        warn!("Cannot migrate {}: {}", LEGACY_CONF_PATH, e);
+    }
+    read_config_from(&path)
+}
+
+/// Reads and migrates the config at `path`.
+///
+/// A file that cannot be parsed or migrated is moved aside to a timestamped backup
+/// instead of being overwritten by the next save. Files that cannot be read, e.g. for
+/// lack of permissions, are left alone.
+pub fn read_config_from(path: &Path) -> Option<UserConfig> {
+    info!("Reading config {}", path.display());
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            info!("Config file not found");
+            return None;
+        }
+        Err(e) => {
+            error!("Cannot read config {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let (config, version) = match parse_config(&contents) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            error!("Cannot parse config {}: {}", path.display(), e);
+            match backup_config(path, true) {
+                Ok(backup) => warn!("Unreadable config moved to {}", backup.display()),
+                Err(e) => error!("Cannot back up unreadable config: {}", e),
+            }
+            return None;
+        }
+    };
+    if version > CONFIG_VERSION {
+        warn!(
+            "Config version {} is newer than the supported version {}, unknown settings are ignored",
+            version, CONFIG_VERSION
+        );
+        match backup_config(path, false) {
+            Ok(backup) => info!("Newer config copied to {}", backup.display()),
+            Err(e) => {
+                error!("Cannot keep a copy of the newer config: {}", e);
+                return None;
+            }
+        }
+    }
+    Some(config)
+}
+
+/// Parses and migrates a config, returns it with the version it was written in.
+fn parse_config(contents: &[u8]) -> Result<(UserConfig, u32), Error> {
+    let value: Value = serde_json::from_slice(contents)?;
+    let version = config_version(&value)?;
+    let mut config: UserConfig = serde_json::from_value(migrate(value, version)?)?;
+    config.version = CONFIG_VERSION;
+    config.ensure_profile();
+    Ok((config, version))
+}
+
+/// Copies or moves `path` to `<path>.<timestamp>.bak` and returns the backup path.
+fn backup_config(path: &Path, remove: bool) -> Result<PathBuf, Error> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(
+        ".{}.bak",
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+    let backup = PathBuf::from(backup);
+    match remove {
+        true => fs::rename(path, &backup)?,
+        false => {
+            fs::copy(path, &backup)?;
+        }
+    }
+    Ok(backup)
+}
+
+pub fn save_config(config: &UserConfig) -> Result<(), Error> {
+    save_config_to(&config_path()?, config)
+}
+
+/// Saves every profile, `UserConfig::remember_me` only decides whether user names are kept.
+pub fn save_config_to(path: &Path, config: &UserConfig) -> Result<(), Error> {
+    info!("Saving config {}", path.display());
+    let config = UserConfig {
+        profiles: config
+            .profiles
+            .iter()
+            .map(|p| Profile {
+                tunnel_params: remove_credentials(&p.tunnel_params, config.remember_me),
+                ..p.clone()
+            })
+            .collect(),
+        ..config.clone()
+    };
+    write_atomically(path, serde_json::to_string(&config)?.as_bytes())
+}
+
+/// Writes to a temporary file next to `path` and renames it over `path`,
+/// so a crash never leaves a truncated config behind. The file is only readable by the user.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+    let tmp_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Moves a config that older versions wrote to the working directory to `path`, unless there is one.
+pub fn migrate_legacy_config(legacy_path: &Path, path: &Path) -> Result<(), Error> {
+    if path.exists() || !legacy_path.is_file() {
+        return Ok(());
+    }
+    info!(
+        "Migrating config {} to {}",
+        legacy_path.display(),
+        path.display()
+    );
+    write_atomically(path, &fs::read(legacy_path)?)?;
+    fs::remove_file(legacy_path)
+}
+
+/// Migration steps between on-disk versions: `MIGRATIONS[n]` upgrades version `n + 1` to `n + 2`.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v1_to_v2];
+const _: () = assert!(MIGRATIONS.len() + 1 == CONFIG_VERSION as usize);
+
+/// Configs written before the `version` field was introduced are version 1.
+fn config_version(value: &Value) -> Result<u32, Error> {
+    match value.get("version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid config version")),
+    }
+}
+
+/// Runs every migration from `version` up to `CONFIG_VERSION`.
+fn migrate(mut value: Value, version: u32) -> Result<Value, Error> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "config is not a JSON object"))?;
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        debug!("Migrating config from version {}", from + 1);
+        step(object);
+    }
+    Ok(value)
+}
+
+/// Version 1 held a single `tunnel_params`, version 2 has a list of named profiles.
+fn migrate_v1_to_v2(config: &mut Map<String, Value>) {
+    let profile = serde_json::json!({
+        "name": DEFAULT_PROFILE_NAME,
+        "tunnel_params": config.remove("tunnel_params").unwrap_or_default(),
+    });
+    config.insert("profiles".to_string(), Value::Array(vec![profile]));
+    config.insert("last_profile".to_string(), DEFAULT_PROFILE_NAME.into());
+}
+
+fn remove_credentials(params: &TunnelParams, keep_user_name: bool) -> TunnelParams {
+    debug!("Removing password");
+    let mut params = params.clone();
+    params.password = "".to_string();
+    params.sso_token = None;
+    if !keep_user_name {
+        params.user_name = "".to_string();
+    }
+    params
+}
+
+impl UserConfig {
+    /// Makes sure there is at least one profile to select.
+    pub fn ensure_profile(&mut self) {
+        if self.profiles.is_empty() {
+            warn!("Config has no profiles, adding the default one");
+            self.profiles.push(Profile::new(DEFAULT_PROFILE_NAME));
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// The last used profile, or the first one when it is not set or no longer exists.
+    pub fn current_profile(&self) -> &Profile {
+        self.last_profile
+            .as_deref()
+            .and_then(|name| self.profile(name))
+            .unwrap_or(&self.profiles[0])
+    }
+
+    pub fn current_params_mut(&mut self) -> &mut TunnelParams {
+        let index = self
+            .last_profile
+            .as_deref()
+            .and_then(|name| self.profiles.iter().position(|p| p.name == name))
+            .unwrap_or(0);
+        &mut self.profiles[index].tunnel_params
+    }
+
+    pub fn select_profile(&mut self, name: &str) -> Result<(), Error> {
+        if self.profile(name).is_none() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Profile {} does not exist", name),
+            ));
+        }
+        self.last_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Returns `base`, or `base` with a number appended if that name is already taken.
+    pub fn unique_profile_name(&self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut counter = 2;
+        while self.profile(&name).is_some() {
+            name = format!("{} {}", base, counter);
+            counter += 1;
+        }
+        name
+    }
+
+    /// Adds a new profile with default settings and selects it.
+    pub fn create_profile(&mut self, name: &str) -> Result<(), Error> {
+        self.check_new_name(name)?;
+        self.profiles.push(Profile::new(name));
+        self.select_profile(name)
+    }
+
+    /// Adds a new profile with `params`, e.g. imported from a file, and selects it.
+    pub fn import_profile(&mut self, name: &str, params: TunnelParams) -> Result<(), Error> {
+        self.create_profile(name)?;
+        *self.current_params_mut() = params;
+        Ok(())
+    }
+
+    /// Copies the settings of `name` into a new profile and selects it.
+    pub fn duplicate_profile(&mut self, name: &str, new_name: &str) -> Result<(), Error> {
+        self.check_new_name(new_name)?;
+        let source = self.profile(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Profile {} does not exist", name),
+            )
+        })?;
+        let copy = Profile {
+            name: new_name.to_string(),
+            ..source.clone()
+        };
+        self.profiles.push(copy);
+        self.select_profile(new_name)
+    }
+
+    pub fn rename_profile(&mut self, name: &str, new_name: &str) -> Result<(), Error> {
+        if name == new_name {
+            return Ok(());
+        }
+        self.check_new_name(new_name)?;
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("Profile {} does not exist", name),
+                )
+            })?;
+        profile.name = new_name.to_string();
+        if self.last_profile.as_deref() == Some(name) {
+            self.last_profile = Some(new_name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Removes a profile, the last remaining one cannot be deleted.
+    pub fn delete_profile(&mut self, name: &str) -> Result<(), Error> {
+        if self.profiles.len() <= 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The last profile cannot be deleted",
+            ));
+        }
+        let before = self.profiles.len();
+        self.profiles.retain(|p| p.name != name);
+        if self.profiles.len() == before {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Profile {} does not exist", name),
+            ));
+        }
+        if self.last_profile.as_deref() == Some(name) {
+            self.last_profile = Some(self.profiles[0].name.clone());
+        }
+        Ok(())
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<(), Error> {
+        if name.trim().is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Profile name is required",
+            ));
+        }
+        if self.profile(name).is_some() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("Profile {} already exists", name),
+            ));
+        }
+        Ok(())
+    }
+}
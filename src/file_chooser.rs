@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+/// Files offered by a file dialog, e.g. `*.pem`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFilter {
+    pub name: &'static str,
+    pub patterns: &'static [&'static str],
+}
+
+pub const CERTIFICATE_FILTER: FileFilter = FileFilter {
+    name: "Certificates",
+    patterns: &["*.pem", "*.crt", "*.cer", "*.der"],
+};
+
+/// Local path of a `file://` URI as returned by the portal.
+pub fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// File dialogs over the xdg-desktop-portal D-Bus API, which also works inside Flatpak.
+#[cfg(feature = "file-chooser")]
+mod portal {
+    use futures_util::StreamExt;
+    use log::debug;
+    use std::collections::HashMap;
+    use std::io::{Error, ErrorKind};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use zbus::zvariant::{OwnedValue, Value};
+    use zbus::{Connection, Proxy};
+
+    use super::{file_uri_path, FileFilter};
+
+    const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+    const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+    /// `Response` code of a dialog the user closed without choosing.
+    const RESPONSE_CANCELLED: u32 = 1;
+
+    static REQUEST_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    fn portal_error(e: zbus::Error) -> Error {
+        Error::other(format!("file chooser portal: {}", e))
+    }
+
+    /// Shows the file dialog of the desktop and returns the chosen files, none when cancelled.
+    pub async fn open_files(
+        title: &str,
+        multiple: bool,
+        filter: &FileFilter,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let connection = Connection::session().await.map_err(portal_error)?;
+        let token = format!(
+            "snx_rs_gui_{}_{}",
+            std::process::id(),
+            REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        // The response arrives on a request object derived from the token, listen before asking.
+        let sender = connection
+            .unique_name()
+            .ok_or_else(|| Error::other("no unique name on the session bus"))?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+        let request = Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            request_path.as_str(),
+            "org.freedesktop.portal.Request",
+        )
+        .await
+        .map_err(portal_error)?;
+        let mut responses = request
+            .receive_signal("Response")
+            .await
+            .map_err(portal_error)?;
+
+        let patterns: Vec<(u32, String)> = filter
+            .patterns
+            .iter()
+            .map(|pattern| (0, pattern.to_string()))
+            .collect();
+        let filters = vec![(filter.name.to_string(), patterns)];
+        let mut options: HashMap<&str, Value> = HashMap::new();
+        options.insert("handle_token", Value::from(token.as_str()));
+        options.insert("multiple", Value::from(multiple));
+        options.insert("filters", Value::from(filters.clone()));
+        options.insert("current_filter", Value::from(filters[0].clone()));
+        let chooser = Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            "org.freedesktop.portal.FileChooser",
+        )
+        .await
+        .map_err(portal_error)?;
+        let _: zbus::zvariant::OwnedObjectPath = chooser
+            .call("OpenFile", &("", title, options))
+            .await
+            .map_err(portal_error)?;
+
+        let response = responses
+            .next()
+            .await
+            .ok_or_else(|| Error::other("file chooser portal went away"))?;
+        let (code, results): (u32, HashMap<String, OwnedValue>) =
+            response.body().deserialize().map_err(portal_error)?;
+        match code {
+            0 => {}
+            RESPONSE_CANCELLED => {
+                debug!("File chooser cancelled");
+                return Ok(Vec::new());
+            }
+            _ => return Err(Error::other("file chooser failed")),
+        }
+        let uris: Vec<String> = match results.get("uris") {
+            Some(uris) => uris
+                .try_clone()
+                .and_then(Vec::try_from)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            None => Vec::new(),
+        };
+        Ok(uris.iter().filter_map(|uri| file_uri_path(uri)).collect())
+    }
+}
+
+#[cfg(feature = "file-chooser")]
+pub use self::portal::open_files;
+
+/// Stand-in when built without the `file-chooser` feature, paths have to be typed.
+#[cfg(not(feature = "file-chooser"))]
+pub async fn open_files(
+    _title: &str,
+    _multiple: bool,
+    _filter: &FileFilter,
+) -> Result<Vec<PathBuf>, std::io::Error> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Built without file chooser support",
+    ))
+}
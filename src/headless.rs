@@ -0,0 +1,201 @@
+use log::{debug, info};
+use serde::Serialize;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+use crate::certificate::bundle_ca_certs;
+use crate::client::{ConnectResponse, TunnelClient, CONNECT_TIMEOUT};
+use crate::config::ca_bundle_path;
+use crate::model::{ConnectionStatus, LoginType, Profile, TunnelParams};
+use crate::pinning::check_server;
+use crate::profiles::profile_password;
+use crate::secrets::SecretStore;
+use crate::status::{connection_details, format_duration};
+use crate::validation::validate_params;
+
+/// Password for `--connect` when the profile does not save it in the secret service.
+pub const PASSWORD_ENV: &str = "SNX_RS_GUI_PASSWORD";
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Connection status printed by `--status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub service_running: bool,
+    pub connected: bool,
+    #[serde(flatten)]
+    pub details: ConnectionStatus,
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let details = &self.details;
+        match self.connected {
+            true => writeln!(f, "Connection status: connected")?,
+            false => writeln!(f, "Connection status: disconnected")?,
+        }
+        if let Some(since) = details.connected_since_time() {
+            let duration = format_duration(
+                details
+                    .session_duration(chrono::Local::now())
+                    .unwrap_or_default(),
+            );
+            writeln!(
+                f,
+                "Connected since: {} ({})",
+                since.format("%Y-%m-%d %H:%M:%S"),
+                duration
+            )?;
+        }
+        if let Some(server) = &details.server_name {
+            match details.tunnel_type {
+                Some(tunnel_type) => writeln!(f, "Server: {} ({})", server, tunnel_type)?,
+                None => writeln!(f, "Server: {}", server)?,
+            }
+        }
+        if let Some(ip) = &details.ip_address {
+            writeln!(f, "IP address: {}", ip)?;
+        }
+        if let Some(dns) = details.dns_servers.as_ref().filter(|d| !d.is_empty()) {
+            writeln!(f, "DNS servers: {}", dns.join(", "))?;
+        }
+        if let Some(routes) = details.routes.as_ref().filter(|r| !r.is_empty()) {
+            writeln!(f, "Routes: {}", routes.join(", "))?;
+        }
+        match self.service_running {
+            true => write!(f, "Snx-rs service status: running"),
+            false => write!(f, "Snx-rs service status: stopped"),
+        }
+    }
+}
+
+pub async fn status(client: &TunnelClient) -> StatusReport {
+    match client.get_status().await {
+        Ok(response) => {
+            let details = connection_details(response);
+            StatusReport {
+                service_running: true,
+                connected: details.is_some(),
+                details: details.unwrap_or_default(),
+            }
+        }
+        Err(e) => {
+            debug!("Service is not responding: {}", e);
+            StatusReport {
+                service_running: false,
+                connected: false,
+                details: ConnectionStatus::default(),
+            }
+        }
+    }
+}
+
+/// The password for `--connect`, saved in the secret service or taken from `PASSWORD_ENV`.
+pub async fn connect_password(
+    profile: &Profile,
+    store: Option<Arc<dyn SecretStore>>,
+) -> Result<String, Error> {
+    profile_password(profile, store, std::env::var(PASSWORD_ENV).ok())
+        .await
+        .map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "{}: check Save password in the GUI or set {}",
+                    e, PASSWORD_ENV
+                ),
+            )
+        })
+}
+
+/// Connects with `profile` and waits until the tunnel is up.
+///
+/// MFA challenges are answered with `read_code`, which gets the prompt of the service.
+pub async fn connect(
+    client: &TunnelClient,
+    profile: &Profile,
+    password: String,
+    mut read_code: impl FnMut(&str) -> Result<String, Error>,
+) -> Result<StatusReport, Error> {
+    if profile.tunnel_params.login_type == LoginType::SsoAzure {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "SSO logins need the GUI",
+        ));
+    }
+    let unsupported = client
+        .protocol()
+        .unsupported_options(&profile.tunnel_params);
+    if !unsupported.is_empty() {
+        let options: Vec<String> = unsupported.iter().map(ToString::to_string).collect();
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "The {} service protocol does not support {}",
+                client.protocol(),
+                options.join(", ")
+            ),
+        ));
+    }
+    info!("Connecting with profile {}", profile.name);
+    let params = TunnelParams {
+        password,
+        ..profile.tunnel_params.clone()
+    };
+    let validation = validate_params(&params);
+    if validation.has_errors() {
+        let errors: Vec<String> = validation.errors().map(ToString::to_string).collect();
+        return Err(Error::new(ErrorKind::InvalidInput, errors.join(", ")));
+    }
+    if !params.pinned_fingerprints.is_empty() {
+        check_server(&params).await?.verify()?;
+    }
+    let params = bundle_ca_certs(params, &ca_bundle_path()?)?;
+    let command_client = client.clone().with_timeout(CONNECT_TIMEOUT);
+    let mut response = command_client
+        .connect(params.clone())
+        .await
+        .map_err(Error::other)?;
+    loop {
+        match response {
+            ConnectResponse::Accepted => break,
+            ConnectResponse::Challenge(prompt) => {
+                let code = read_code(&prompt)?;
+                response = command_client
+                    .send_challenge_code(code.trim().to_string(), params.clone())
+                    .await
+                    .map_err(Error::other)?;
+            }
+            ConnectResponse::IdentityProvider(_) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "The server asks for an SSO login, which needs the GUI",
+                ))
+            }
+        }
+    }
+    wait_for_tunnel(client, CONNECT_TIMEOUT).await
+}
+
+pub async fn disconnect(client: &TunnelClient) -> Result<(), Error> {
+    client.disconnect().await.map_err(Error::other)
+}
+
+async fn wait_for_tunnel(client: &TunnelClient, timeout: Duration) -> Result<StatusReport, Error> {
+    let start = Instant::now();
+    loop {
+        let report = status(client).await;
+        if report.connected {
+            return Ok(report);
+        }
+        if start.elapsed() >= timeout {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "Timed out waiting for the tunnel to come up",
+            ));
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,23 @@
+pub mod certificate;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod file_chooser;
+pub mod headless;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod model;
+pub mod notify;
+pub mod pinning;
+pub mod profiles;
+pub mod protocol;
+pub mod reconnect;
+pub mod secrets;
+pub mod snx_config;
+pub mod sso;
+pub mod state;
+pub mod status;
+pub mod subscription;
+pub mod systemd;
+pub mod tray;
+pub mod validation;
@@ -1,104 +1,800 @@
-use log::{debug, error, info, LevelFilter};
-// use runas::Command;
-use std::fs::File;
-use std::io::{Error, ErrorKind, Write};
-use std::net::UdpSocket;
+use log::{error, info, LevelFilter};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use std::{fs, str};
-use tokio::time::sleep;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::time::{sleep, timeout};
 
+use chrono::{DateTime, Local};
 use dioxus::prelude::*;
-use dioxus_desktop::{Config, WindowBuilder};
+use dioxus_desktop::wry::application::dpi::LogicalSize;
+use dioxus_desktop::wry::application::event::{Event, WindowEvent};
+use dioxus_desktop::{
+    use_window, use_wry_event_handler, Config, DesktopContext, WindowBuilder, WindowCloseBehaviour,
+};
 
-use crate::model::{TunnelParams, TunnelType, UserConfig};
+use snx_rs_gui::certificate::{bundle_ca_certs, CertificateCache};
+use snx_rs_gui::cli::{Action, CliArgs, USAGE};
+use snx_rs_gui::client::{
+    check_service_address, resolve_service_address, CommandError, ConnectResponse, TunnelClient,
+    CONNECT_TIMEOUT,
+};
+use snx_rs_gui::config::{ca_bundle_path, read_config, save_config};
+use snx_rs_gui::file_chooser::{open_files, CERTIFICATE_FILTER};
+use snx_rs_gui::headless;
+use snx_rs_gui::model::{
+    self, ConnectionStatus, Profile, TunnelParams, TunnelServiceResponse, TunnelType, UserConfig,
+};
+use snx_rs_gui::notify::{notify, ConnectionNotification};
+use snx_rs_gui::pinning::{check_server, Fingerprint, ServerCheck};
+use snx_rs_gui::profiles::{self, profile_password};
+use snx_rs_gui::protocol::Protocol;
+use snx_rs_gui::reconnect::Reconnect;
+use snx_rs_gui::secrets::{default_secret_store, move_password, SecretStore};
+use snx_rs_gui::sso::{RedirectListener, SSO_REDIRECT_ADDRESS};
+use snx_rs_gui::state::{ConnectionEvent, ConnectionState};
+use snx_rs_gui::status::{connection_details, format_duration};
+use snx_rs_gui::subscription::{
+    watch_status, PollPolicy, PollingSource, StatusSubscription, WatchHints,
+};
+use snx_rs_gui::systemd::{
+    journal, SystemdService, UnitAction, UnitState, JOURNAL_LINES, SNX_RS_UNIT,
+};
+use snx_rs_gui::tray::{spawn_tray, TrayAction, TrayHandle, TrayModel};
+use snx_rs_gui::validation::{validate_params, validate_params_cached, Field, Issue, Severity};
 
-mod model;
+/// How long the MFA dialog waits for the user to enter a code.
+const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long the SSO window waits for the identity provider to redirect back.
+const SSO_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Command sent to the service, kept so that it can be retried after a failure.
+#[derive(Clone)]
+enum Command {
+    Connect(TunnelParams),
+    /// Answer to an MFA challenge of the pending `Connect`.
+    ChallengeCode(String, TunnelParams),
+    Disconnect,
+}
+
+/// snx-rs config file dialog open in the profile bar.
+#[derive(Clone, Copy, PartialEq)]
+enum ProfileFile {
+    Import,
+    Export,
+}
+
+/// Second factor requested by the service while connecting.
+#[derive(Clone)]
+struct Challenge {
+    prompt: String,
+    params: TunnelParams,
+}
+
+/// State shared by the commands running in the background.
+#[derive(Clone)]
+struct CommandState {
+    command_error: UseState<Option<(String, Command)>>,
+    connection_state: UseState<ConnectionState>,
+    /// Bumped for every connect attempt, so that timeouts of earlier attempts are ignored.
+    connect_attempt: UseRef<u64>,
+    challenge: UseState<Option<Challenge>>,
+    /// Main window, used to open the SSO login window.
+    window: DesktopContext,
+    /// Asked for a fresh status once the service took a command.
+    subscription: StatusSubscription,
+    /// Certificate the gateway presented on the last connect, to pin it or warn about it.
+    server_check: UseState<Option<ServerCheck>>,
+}
+
+impl CommandState {
+    fn dispatch(&self, event: ConnectionEvent) {
+        self.connection_state.modify(|s| s.next(&event));
+    }
+
+    fn next_attempt(&self) -> u64 {
+        self.connect_attempt.with_mut(|a| {
+            *a += 1;
+            *a
+        })
+    }
+
+    fn is_current(&self, attempt: u64) -> bool {
+        *self.connect_attempt.read() == attempt
+    }
+
+    /// Sends `Connect` until the service accepts or refuses it, logging in via SSO when asked to.
+    async fn connect(&self, client: &TunnelClient, mut params: TunnelParams) {
+        loop {
+            let result = client.connect(params.clone()).await;
+            match self.connect_result(result, params).await {
+                Some(next) => params = next,
+                None => return,
+            }
+        }
+    }
+
+    /// Keeps a `Connect` that failed before it was sent in `command_error`, to retry it.
+    fn connect_failed(&self, context: &str, e: Error, params: TunnelParams) {
+        error!("{}: {}", context, e);
+        self.dispatch(ConnectionEvent::ConnectFailed(e.to_string()));
+        self.command_error
+            .set(Some((e.to_string(), Command::Connect(params))));
+    }
+
+    /// Moves the connection state along for `command` before it is sent.
+    fn start(&self, command: &Command) {
+        // Starting over or disconnecting ends whatever an earlier attempt is waiting for.
+        self.next_attempt();
+        match command {
+            Command::Connect(_) => self.dispatch(ConnectionEvent::ConnectRequested),
+            Command::ChallengeCode(..) => self.challenge.set(None),
+            Command::Disconnect => {
+                self.challenge.set(None);
+                self.dispatch(ConnectionEvent::DisconnectRequested);
+            }
+        }
+    }
+
+    /// Sends a `start`ed `command`, keeping it in `command_error` when it fails.
+    async fn run(self, client: TunnelClient, command: Command) {
+        match command {
+            Command::Connect(params) => {
+                let client = client.with_timeout(CONNECT_TIMEOUT);
+                let bundled =
+                    ca_bundle_path().and_then(|path| bundle_ca_certs(params.clone(), &path));
+                let bundled = match bundled {
+                    Ok(bundled) => bundled,
+                    Err(e) => {
+                        return self.connect_failed("Cannot bundle the CA certificates", e, params)
+                    }
+                };
+                if params.pinned_fingerprints.is_empty() {
+                    // Offered for pinning, without holding up the connection.
+                    let offer = async {
+                        match check_server(&params).await {
+                            Ok(check) => self.server_check.set(Some(check)),
+                            Err(e) => info!("Cannot fetch the server certificate: {}", e),
+                        }
+                    };
+                    tokio::join!(offer, self.connect(&client, bundled));
+                    return;
+                }
+                let checked = check_server(&params).await.and_then(|check| {
+                    let result = check.verify();
+                    self.server_check.set(Some(check));
+                    result
+                });
+                match checked {
+                    Ok(()) => self.connect(&client, bundled).await,
+                    Err(e) => self.connect_failed("Server certificate check failed", e, params),
+                }
+            }
+            Command::ChallengeCode(code, params) => {
+                let client = client.with_timeout(CONNECT_TIMEOUT);
+                let result = client.send_challenge_code(code, params.clone()).await;
+                if let Some(params) = self.connect_result(result, params).await {
+                    self.connect(&client, params).await;
+                }
+            }
+            Command::Disconnect => match client.disconnect().await {
+                Ok(()) => {
+                    self.dispatch(ConnectionEvent::DisconnectSucceeded);
+                    self.command_error.set(None);
+                    self.subscription.refresh();
+                }
+                Err(e) => {
+                    error!("Error: {}", e);
+                    self.dispatch(ConnectionEvent::DisconnectFailed(e.to_string()));
+                    self.command_error
+                        .set(Some((e.to_string(), Command::Disconnect)));
+                }
+            },
+        }
+    }
+
+    /// Gives up on a pending connect attempt and its MFA challenge.
+    fn cancel_connect(&self) {
+        self.next_attempt();
+        self.challenge.set(None);
+        self.dispatch(ConnectionEvent::ConnectCancelled);
+    }
+
+    /// Handles the answer to a `Connect` or `ChallengeCode`, waiting for the user while a challenge
+    /// or SSO login is open. Returns the params to connect again with after an SSO login.
+    async fn connect_result(
+        &self,
+        result: Result<ConnectResponse, CommandError>,
+        params: TunnelParams,
+    ) -> Option<TunnelParams> {
+        match result {
+            Ok(ConnectResponse::Accepted) => {
+                let attempt = self.next_attempt();
+                self.dispatch(ConnectionEvent::ConnectSucceeded);
+                self.command_error.set(None);
+                self.subscription.refresh();
+                // The service accepted, fail unless a status poll sees the tunnel come up in time.
+                sleep(CONNECT_TIMEOUT).await;
+                if self.is_current(attempt) {
+                    self.dispatch(ConnectionEvent::ConnectTimedOut);
+                }
+            }
+            Ok(ConnectResponse::Challenge(prompt)) => {
+                let attempt = self.next_attempt();
+                self.command_error.set(None);
+                self.challenge.set(Some(Challenge {
+                    prompt,
+                    params: params.clone(),
+                }));
+                sleep(CHALLENGE_TIMEOUT).await;
+                if self.is_current(attempt) {
+                    let message = "MFA challenge timed out".to_string();
+                    self.challenge.set(None);
+                    self.dispatch(ConnectionEvent::ConnectFailed(message.clone()));
+                    self.command_error
+                        .set(Some((message, Command::Connect(params))));
+                }
+            }
+            Ok(ConnectResponse::IdentityProvider(url)) => {
+                let attempt = self.next_attempt();
+                self.command_error.set(None);
+                let result = self.sso_login(&url).await;
+                if !self.is_current(attempt) {
+                    // Cancelled by a disconnect in the meantime.
+                    return None;
+                }
+                match result {
+                    Ok(token) => {
+                        return Some(TunnelParams {
+                            sso_token: Some(token),
+                            ..params
+                        })
+                    }
+                    Err(e) => {
+                        error!("SSO login failed: {}", e);
+                        self.dispatch(ConnectionEvent::ConnectFailed(e.to_string()));
+                        self.command_error
+                            .set(Some((e.to_string(), Command::Connect(params))));
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                self.dispatch(ConnectionEvent::ConnectFailed(e.to_string()));
+                // Challenge codes are single use, so a retry starts over with a new `Connect`.
+                self.command_error
+                    .set(Some((e.to_string(), Command::Connect(params))));
+            }
+        }
+        None
+    }
+
+    /// Shows the identity provider's login page in a second window and waits for its redirect.
+    async fn sso_login(&self, url: &str) -> Result<String, Error> {
+        let listener = RedirectListener::bind(SSO_REDIRECT_ADDRESS).await?;
+        let url = listener.login_url(url)?;
+        info!("Opening SSO login window for {}", url);
+        let window = self.window.new_window(
+            VirtualDom::new_with_props(SsoWindow, SsoWindowProps { url }),
+            Config::default().with_window(
+                WindowBuilder::new()
+                    .with_title("snx-rs-gui - Sign in")
+                    .with_inner_size(LogicalSize::new(600.0, 700.0)),
+            ),
+        );
+        let result = timeout(SSO_TIMEOUT, listener.accept_token())
+            .await
+            .unwrap_or_else(|_| Err(Error::new(ErrorKind::TimedOut, "SSO login timed out")));
+        if let Some(window) = window.upgrade() {
+            window.close();
+        }
+        result
+    }
+}
+
+/// Login window navigating to the identity provider, which redirects to the `RedirectListener`.
+#[inline_props]
+fn SsoWindow(cx: Scope, url: String) -> Element {
+    let window = use_window(cx);
+    cx.use_hook(|| window.webview.load_url(url));
+    cx.render(rsx! {
+        span { "Loading {url}…" }
+    })
+}
+
+struct AppProps {
+    config: UserConfig,
+    /// Service address from the command line or environment, takes precedence over the config.
+    service_address_override: Option<String>,
+    secret_store: Option<Arc<dyn SecretStore>>,
+    /// `None` when the desktop has no tray.
+    tray: Option<TrayHandle>,
+    tray_actions: Mutex<Option<UnboundedReceiver<TrayAction>>>,
+}
 
 fn main() {
-    dioxus_logger::DioxusLogger::new(LevelFilter::Info)
+    let args = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+    // Keep the output of the headless commands readable by scripts.
+    let log_level = match args.action {
+        Some(_) if args.json => LevelFilter::Off,
+        Some(_) => LevelFilter::Warn,
+        None => LevelFilter::Info,
+    };
+    dioxus_logger::DioxusLogger::new(log_level)
         .use_format("[{LEVEL}] {PATH} - {ARGS}")
         .build()
         .expect("Failed to initialize logger");
-    const MAX_PACKET_SIZE: usize = 1_000_000;
-    const USER_CONF_PATH: &str = "user-config.json";
-    static SERVER_ADDRESS: &str = "127.0.0.1:7779";
     let title = "snx-rs-gui";
-    let user_config = read_config().unwrap_or_else(|| UserConfig {
-        tunnel_params: TunnelParams::default(),
-        remember_me: false,
-    });
+    let service_address_override = args.service_address_override();
+    if let Some(address) = &service_address_override {
+        if let Err(e) = check_service_address(address) {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(action) = args.action {
+        std::process::exit(run_headless(action, &args, service_address_override));
+    }
+    let user_config = read_config().unwrap_or_default();
+    let (tray_sender, tray_receiver) = unbounded_channel();
+    let tray = spawn_tray(
+        TrayModel::new(
+            &ConnectionState::default(),
+            false,
+            &user_config.current_profile().name,
+        ),
+        tray_sender,
+    );
+    // Closing the window only hides it while the tray can bring it back.
+    let close_behaviour = match tray {
+        Some(_) => WindowCloseBehaviour::LastWindowHides,
+        None => WindowCloseBehaviour::LastWindowExitsApp,
+    };
     info!("Starting application");
     dioxus_desktop::launch_with_props(
         app,
-        user_config,
-        Config::default().with_window(
-            WindowBuilder::new()
-                .with_title(title)
-                .with_resizable(true)
-                .with_inner_size(dioxus_desktop::wry::application::dpi::LogicalSize::new(
-                    600.0, 600.0,
-                )),
-        ),
+        AppProps {
+            config: user_config,
+            service_address_override,
+            secret_store: default_secret_store(),
+            tray,
+            tray_actions: Mutex::new(Some(tray_receiver)),
+        },
+        Config::default()
+            .with_close_behaviour(close_behaviour)
+            .with_window(
+                WindowBuilder::new()
+                    .with_title(title)
+                    .with_resizable(true)
+                    .with_inner_size(LogicalSize::new(600.0, 600.0)),
+            ),
     );
 
-    fn app(cx: Scope<UserConfig>) -> Element {
-        let config = cx.props.tunnel_params.clone();
-
-        let username = use_ref(cx, || config.user_name);
+    fn app(cx: Scope<AppProps>) -> Element {
+        let user_config = use_ref(cx, || cx.props.config.clone());
+        // Config as last written, renders only save when it changed.
+        let saved_config = use_ref(cx, || cx.props.config.clone());
         let password = use_ref(cx, String::new);
-        let server_address = use_ref(cx, || config.server_name);
-        let log_level = use_ref(cx, || config.log_level);
-        let reauth = use_state(cx, || config.reauth);
-        let search_domains = use_ref(cx, || config.search_domains.join(" "));
-        let default_route = use_state(cx, || config.default_route);
-        let no_routing = use_state(cx, || config.no_routing);
-        let no_dns = use_state(cx, || config.no_dns);
-        let no_cert_check = use_state(cx, || config.no_cert_check);
-        let tunnel_type = use_ref(cx, || config.tunnel_type);
-        let ca_cert = use_ref(cx, || config.ca_cert);
-        let login_type = use_ref(cx, || config.login_type);
-
-        let remember_me = use_state(cx, || cx.props.remember_me);
+
+        let service_address_override = &cx.props.service_address_override;
+        let effective_service_address = || {
+            service_address_override
+                .clone()
+                .unwrap_or_else(|| user_config.read().service_address.clone())
+        };
+        let service_address_value = effective_service_address();
+        // Looked up in the background whenever the address changes, `None` until then.
+        let resolved_address = use_ref(cx, || None::<Result<SocketAddr, String>>);
+        use_future(cx, (&service_address_value,), |(address,)| {
+            let resolved_address = resolved_address.to_owned();
+            async move {
+                resolved_address.set(None);
+                let result = resolve_service_address(&address).await;
+                resolved_address.set(Some(result.map_err(|e| e.to_string())));
+            }
+        });
+        let service_address_error = match &*resolved_address.read() {
+            Some(Err(e)) => Some(e.clone()),
+            _ => None,
+        };
 
         let settings_expanded = use_state(cx, || false);
+        let renaming_profile = use_state(cx, || false);
+        let profile_name = use_ref(cx, String::new);
+        let profile_error = use_state(cx, || None::<String>);
+        let profile_file = use_state(cx, || None::<ProfileFile>);
+        let profile_file_path = use_ref(cx, String::new);
+        // Lines of the last imported snx-rs config file that were skipped.
+        let import_report = use_state(cx, Vec::<String>::new);
+        let domain_input = use_ref(cx, String::new);
+        let domain_error = use_state(cx, || None::<String>);
+        let ca_cert_path = use_ref(cx, String::new);
+        // Why the file chooser could not add CA certificates.
+        let ca_cert_error = use_state(cx, || None::<String>);
+        // The CA files are only read again when they change, not on every render.
+        let certificate_cache = use_ref(cx, CertificateCache::default);
 
-        let missing_username = use_state(cx, || false);
-        let missing_password = use_state(cx, || false);
-        let missing_server_address = use_state(cx, || false);
+        // Errors of the login form are shown once Connect was clicked.
+        let validation_shown = use_state(cx, || false);
 
         let status = use_state(cx, || false);
-        let connection_status = use_state(cx, || false);
+        let connection_state = use_state(cx, ConnectionState::default);
+        let connect_attempt = use_ref(cx, || 0u64);
+        let connection_details = use_state(cx, || None::<ConnectionStatus>);
+        let detected_protocol = use_state(cx, || None::<Protocol>);
+        let active_params = use_state(cx, || None::<TunnelParams>);
+        let command_error = use_state(cx, || None::<(String, Command)>);
+        let challenge = use_state(cx, || None::<Challenge>);
+        let challenge_code = use_ref(cx, String::new);
+        let reconnect = use_state(cx, Reconnect::default);
+        // Connection state seen by the last render, to follow its changes.
+        let observed_state = use_ref(cx, ConnectionState::default);
+        let window = use_window(cx);
+        let window_visible = use_state(cx, || true);
+        let subscription: &StatusSubscription = cx.use_hook(StatusSubscription::default);
+        let server_check = use_state(cx, || None::<ServerCheck>);
+        let pin_input = use_ref(cx, String::new);
+        // Why a fingerprint could not be pinned or fetched from the settings panel.
+        let pin_error = use_state(cx, || None::<String>);
+        let commands: &CommandState = cx.use_hook(|| CommandState {
+            command_error: command_error.to_owned(),
+            connection_state: connection_state.to_owned(),
+            connect_attempt: connect_attempt.to_owned(),
+            challenge: challenge.to_owned(),
+            window: window.clone(),
+            subscription: subscription.clone(),
+            server_check: server_check.to_owned(),
+        });
+        use_wry_event_handler(cx, {
+            let window = window.clone();
+            let window_visible = window_visible.to_owned();
+            move |event, _| {
+                let Event::WindowEvent {
+                    event, window_id, ..
+                } = event
+                else {
+                    return;
+                };
+                if *window_id != window.id() {
+                    return;
+                }
+                let visible = match event {
+                    // Hidden rather than closed while there is a tray icon.
+                    WindowEvent::CloseRequested => false,
+                    WindowEvent::Focused(_) | WindowEvent::Resized(_) | WindowEvent::Moved(_) => {
+                        window.is_visible() && !window.is_minimized()
+                    }
+                    _ => return,
+                };
+                if visible != *window_visible.current() {
+                    window_visible.set(visible);
+                }
+            }
+        });
+
+        let tray_actions = use_ref(cx, VecDeque::<TrayAction>::new);
+        let tray_model = use_ref(cx, || None::<TrayModel>);
+        cx.use_hook(|| {
+            let Some(mut receiver) = cx.props.tray_actions.lock().unwrap().take() else {
+                return;
+            };
+            let tray_actions = tray_actions.to_owned();
+            cx.spawn(async move {
+                // Handled on the next render, where the form state is at hand.
+                while let Some(action) = receiver.recv().await {
+                    tray_actions.write().push_back(action);
+                }
+            });
+        });
 
         let status_msg = "Snx-rs service status: ";
         let connection_status_msg = "Connection status: ";
 
-        status_service(cx, status, connection_status);
+        status_service(
+            cx,
+            user_config,
+            resolved_address,
+            status,
+            connection_state,
+            connection_details,
+            detected_protocol,
+            subscription,
+        );
+
+        let details = connection_details.get().clone().unwrap_or_default();
+        let connected_since = details.connected_since_time();
+        let active_server = details
+            .server_name
+            .clone()
+            .or_else(|| active_params.get().as_ref().map(|p| p.server_name.clone()));
+        let active_tunnel_type = details
+            .tunnel_type
+            .or_else(|| active_params.get().as_ref().map(|p| p.tunnel_type));
+
+        let remember_me = user_config.read().remember_me;
+        let notifications = user_config.read().notifications;
+        let reconnect_settings = user_config.read().reconnect;
+        let current_profile = user_config.read().current_profile().clone();
+        let profile_names: Vec<String> = user_config
+            .read()
+            .profiles
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        let config = current_profile.tunnel_params.clone();
+        let search_domains = config.search_domains.clone();
+        // What each CA file holds, read errors and expiry are shown as field issues.
+        let ca_certs: Vec<(usize, String, Vec<String>)> = config
+            .ca_cert
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let details = certificate_cache
+                    .write_silent()
+                    .read(path)
+                    .map(|certs| certs.iter().map(ToString::to_string).collect())
+                    .unwrap_or_default();
+                (index, path.display().to_string(), details)
+            })
+            .collect();
+        let pins: Vec<Fingerprint> = config.pinned_fingerprints.clone();
+        // Certificate to pin or warn about, as long as it belongs to the current profile's gateway.
+        let presented = server_check
+            .get()
+            .as_ref()
+            .filter(|check| check.certificate().server == config.server_name.trim())
+            .and_then(|check| match check {
+                ServerCheck::Unpinned(certificate) if !pins.contains(&certificate.fingerprint) => {
+                    Some((false, certificate.clone()))
+                }
+                ServerCheck::Mismatch(certificate) => Some((true, certificate.clone())),
+                _ => None,
+            });
+        let selected_protocol = user_config.read().protocol;
+        let protocol = selected_protocol
+            .or(*detected_protocol.get())
+            .unwrap_or_default();
+        let unsupported_options = protocol
+            .unsupported_options(&config)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let protocol_value = selected_protocol
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "detect".to_string());
+        let detect_label = match detected_protocol.get() {
+            Some(protocol) => format!("Detect (found {})", protocol),
+            None => "Detect".to_string(),
+        };
+        let service_client = move || match &*resolved_address.read() {
+            Some(Ok(address)) => Ok(TunnelClient::new(*address).with_protocol(protocol)),
+            Some(Err(e)) => Err(Error::new(ErrorKind::InvalidInput, e.clone())),
+            None => Err(Error::new(
+                ErrorKind::WouldBlock,
+                "The service address is not resolved yet",
+            )),
+        };
 
+        let current_profile_name = || user_config.read().current_profile().name.clone();
         let current_settings = || TunnelParams {
-            user_name: username.read().to_string(),
-            server_name: server_address.read().to_string(),
             password: password.read().to_string(),
-            log_level: log_level.read().to_string(),
-            reauth: reauth.get().to_owned(),
-            search_domains: parse_search_domains(search_domains.read().to_string()),
-            default_route: default_route.get().to_owned(),
-            no_routing: no_routing.get().to_owned(),
-            no_dns: no_dns.get().to_owned(),
-            no_cert_check: no_cert_check.get().to_owned(),
-            tunnel_type: tunnel_type.read().to_owned(),
-            ca_cert: ca_cert.read().to_owned(),
-            login_type: login_type.read().to_owned(),
+            ..user_config.read().current_profile().tunnel_params.clone()
+        };
+        let profile_result = |result: Result<(), Error>| match result {
+            Ok(()) => profile_error.set(None),
+            Err(e) => profile_error.set(Some(e.to_string())),
         };
 
-        if remember_me.get().to_owned() {
-            let save_res = save_config(UserConfig {
-                tunnel_params: current_settings(),
-                remember_me: remember_me.get().to_owned(),
+        let validation =
+            validate_params_cached(&current_settings(), &mut certificate_cache.write_silent());
+        let setting_errors = validation.errors().any(|issue| issue.field.is_setting());
+        let issues = |field: Field| -> Vec<Issue> { validation.field(field).cloned().collect() };
+        // Empty fields of the login form are not an error before the first Connect.
+        let form_issues = |field: Field| match **validation_shown {
+            true => issues(field),
+            false => Vec::new(),
+        };
+
+        // Domains typed into the search domain editor, kept in the field when one is invalid.
+        let add_search_domains = move || {
+            let text = domain_input.read().clone();
+            match user_config.with_mut(|c| c.current_params_mut().add_search_domains(&text)) {
+                Ok(()) => {
+                    domain_input.set(String::new());
+                    domain_error.set(None);
+                }
+                Err(e) => domain_error.set(Some(e)),
+            }
+        };
+
+        let file_action = *profile_file.get();
+        let open_profile_file = move |action: ProfileFile| {
+            profile_file_path.set(String::new());
+            import_report.set(Vec::new());
+            profile_file.set(Some(action));
+        };
+
+        let secret_store = &cx.props.secret_store;
+        let load_password = move |profile: Profile| {
+            password.set("".to_string());
+            let Some(store) = secret_store.clone() else {
+                return;
+            };
+            if !profile.save_password {
+                return;
+            }
+            let password = password.to_owned();
+            cx.spawn(async move {
+                let user_name = profile.tunnel_params.user_name;
+                let result =
+                    with_secret_store(store, move |s| s.get_password(&profile.name, &user_name))
+                        .await;
+                match result {
+                    Ok(Some(p)) => password.set(p),
+                    Ok(None) => info!("No saved password"),
+                    Err(e) => error!("Cannot read saved password: {}", e),
+                }
             });
-            info!("Saving config result: {:?}", save_res);
+        };
+        cx.use_hook(|| load_password(current_profile.clone()));
+
+        // Returns `false` when the form is incomplete and the window needs the user's attention.
+        let connect = move || {
+            if !connection_state.can_connect() {
+                return true;
+            }
+            let params = current_settings();
+            validation_shown.set(true);
+            let Ok(client) = service_client() else {
+                return false;
+            };
+            // Warnings are shown, but only errors keep the service from getting the settings.
+            if validate_params(&params).has_errors() {
+                return false;
+            }
+            settings_expanded.set(false);
+            let profile = user_config.read().current_profile().clone();
+            if profile.save_password {
+                let (user_name, password) = (params.user_name.clone(), params.password.clone());
+                spawn_secret_update(cx, secret_store, move |s| {
+                    s.set_password(&profile.name, &user_name, &password)
+                });
+            } else {
+                password.set("".to_string());
+            }
+            active_params.set(Some(TunnelParams {
+                password: "".to_string(),
+                ..params.clone()
+            }));
+            run_command(cx, client, Command::Connect(params), commands);
+            true
+        };
+        let disconnect = move || {
+            let Ok(client) = service_client() else {
+                return;
+            };
+            run_command(cx, client, Command::Disconnect, commands);
         };
 
+        let show_window = || {
+            window.set_visible(true);
+            window.set_focus();
+        };
+        for action in tray_actions.write_silent().drain(..).collect::<Vec<_>>() {
+            match action {
+                TrayAction::Connect => {
+                    if !connect() {
+                        show_window();
+                    }
+                }
+                TrayAction::Disconnect => disconnect(),
+                TrayAction::ShowWindow => show_window(),
+                TrayAction::Quit => std::process::exit(0),
+            }
+        }
+        if *observed_state.read() != **connection_state {
+            let previous = std::mem::replace(
+                &mut *observed_state.write_silent(),
+                connection_state.get().clone(),
+            );
+            let mut settings = user_config.read().reconnect;
+            // Without a password to send, reconnecting would only fail.
+            let params = active_params
+                .get()
+                .clone()
+                .unwrap_or_else(|| current_profile.tunnel_params.clone());
+            let sso = params.login_type == model::LoginType::SsoAzure;
+            let saved_password = current_profile.save_password && secret_store.is_some();
+            settings.enabled &= sso || saved_password || !password.read().is_empty();
+            let next = reconnect.on_state_change(
+                &settings,
+                &previous,
+                connection_state.get(),
+                Local::now(),
+            );
+            if next != **reconnect {
+                if let Reconnect::Waiting { at, attempt } = next {
+                    info!(
+                        "Reconnecting in {}s, attempt {}",
+                        (at - Local::now()).num_seconds(),
+                        attempt
+                    );
+                    let waiting = next.clone();
+                    let reconnect = reconnect.to_owned();
+                    let commands = commands.clone();
+                    let profile = current_profile.clone();
+                    let store = secret_store.clone();
+                    let typed_password = password.read().clone();
+                    let client = service_client();
+                    cx.spawn(async move {
+                        sleep((at - Local::now()).to_std().unwrap_or_default()).await;
+                        let password = match sso {
+                            true => Ok(String::new()),
+                            false => profile_password(&profile, store, Some(typed_password)).await,
+                        };
+                        // Cancelled, or the connection changed while waiting.
+                        if *reconnect.current() != waiting {
+                            return;
+                        }
+                        let (password, client) = match (password, client) {
+                            (Ok(password), Ok(client)) => (password, client),
+                            (Err(e), _) | (_, Err(e)) => {
+                                error!("Cannot reconnect: {}", e);
+                                reconnect.set(Reconnect::Idle);
+                                return;
+                            }
+                        };
+                        reconnect.set(waiting.start_attempt());
+                        let command = Command::Connect(TunnelParams { password, ..params });
+                        commands.start(&command);
+                        commands.run(client, command).await;
+                    });
+                }
+                reconnect.set(next);
+            }
+        }
+        // The tray icon, notifications and reconnecting need the status while the window is hidden.
+        let background =
+            cx.props.tray.is_some() || notifications.any_enabled() || reconnect_settings.enabled;
+        subscription.set_hints(WatchHints {
+            state: connection_state.get().clone(),
+            service_running: **status,
+            visible: **window_visible,
+            background,
+        });
+        if let Some(tray) = &cx.props.tray {
+            let model =
+                TrayModel::new(connection_state.get(), *status.get(), &current_profile.name);
+            if tray_model.read().as_ref() != Some(&model) {
+                *tray_model.write_silent() = Some(model.clone());
+                tray.update(model);
+            }
+        }
+
+        if *saved_config.read() != *user_config.read() {
+            let config = user_config.read().clone();
+            if let Err(e) = save_config(&config) {
+                error!("Cannot save the config: {}", e);
+            }
+            // Not retried on every render after a failure, the next change tries again.
+            *saved_config.write_silent() = config;
+        }
+
         cx.render(rsx! {
             div {
                 link {
@@ -113,15 +809,167 @@ fn main() {
                     onclick: move |_| {
                         settings_expanded.set(false);
                     },
+                    div { class: "profile-bar",
+                        if **renaming_profile {
+                            rsx! {
+                                input {
+                                    class: "form-input profile-name",
+                                    value: "{profile_name.read()}",
+                                    oninput: move |e| { profile_name.set(e.value.clone()) }
+                                }
+                                button {
+                                    class: "profile-button",
+                                    onclick: move |_| {
+                                        let profile = user_config.read().current_profile().clone();
+                                        let new_name = profile_name.read().trim().to_string();
+                                        let result = user_config.with_mut(|c| c.rename_profile(&profile.name, &new_name));
+                                        if result.is_ok() {
+                                            renaming_profile.set(false);
+                                            spawn_secret_update(cx, secret_store, move |s| {
+                                                move_password(s, &profile.name, &new_name, &profile.tunnel_params.user_name)
+                                            });
+                                        }
+                                        profile_result(result);
+                                    },
+                                    "Save"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    onclick: move |_| {
+                                        renaming_profile.set(false);
+                                        profile_error.set(None);
+                                    },
+                                    "Cancel"
+                                }
+                            }
+                        } else if file_action.is_some() {
+                            rsx! {
+                                input {
+                                    class: "form-input profile-name",
+                                    placeholder: "snx-rs config file",
+                                    value: "{profile_file_path.read()}",
+                                    oninput: move |e| { profile_file_path.set(e.value.clone()) }
+                                }
+                                button {
+                                    class: "profile-button",
+                                    disabled: profile_file_path.read().trim().is_empty(),
+                                    onclick: move |_| {
+                                        let path = PathBuf::from(profile_file_path.read().trim());
+                                        let result = match file_action {
+                                            Some(ProfileFile::Import) => user_config
+                                                .with_mut(|c| profiles::import_profile(c, &path, None))
+                                                .map(|(_, unmapped)| {
+                                                    import_report.set(unmapped.iter().map(ToString::to_string).collect());
+                                                    load_password(user_config.read().current_profile().clone());
+                                                }),
+                                            _ => profiles::export_profile(&user_config.read(), &path, None),
+                                        };
+                                        if result.is_ok() {
+                                            profile_file.set(None);
+                                        }
+                                        profile_result(result);
+                                    },
+                                    if file_action == Some(ProfileFile::Import) { "Import" } else { "Export" }
+                                }
+                                button {
+                                    class: "profile-button",
+                                    onclick: move |_| {
+                                        profile_file.set(None);
+                                        profile_error.set(None);
+                                    },
+                                    "Cancel"
+                                }
+                            }
+                        } else {
+                            rsx! {
+                                select {
+                                    class: "profile-select",
+                                    value: "{current_profile.name}",
+                                    onchange: move |selection| {
+                                        let name = selection.data.value.clone();
+                                        profile_result(user_config.with_mut(|c| c.select_profile(&name)));
+                                        load_password(user_config.read().current_profile().clone());
+                                    },
+                                    profile_names.iter().map(|name| rsx! {
+                                        option { key: "{name}", value: "{name}", "{name}" }
+                                    })
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "New profile",
+                                    onclick: move |_| {
+                                        profile_result(user_config.with_mut(|c| {
+                                            let name = c.unique_profile_name("Profile");
+                                            c.create_profile(&name)
+                                        }));
+                                        load_password(user_config.read().current_profile().clone());
+                                    },
+                                    "New"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "Duplicate profile",
+                                    onclick: move |_| {
+                                        let source = current_profile_name();
+                                        profile_result(user_config.with_mut(|c| {
+                                            let name = c.unique_profile_name(&format!("{} copy", source));
+                                            c.duplicate_profile(&source, &name)
+                                        }));
+                                        load_password(user_config.read().current_profile().clone());
+                                    },
+                                    "Duplicate"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "Rename profile",
+                                    onclick: move |_| {
+                                        profile_name.set(current_profile_name());
+                                        renaming_profile.set(true);
+                                    },
+                                    "Rename"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "Delete profile",
+                                    disabled: profile_names.len() <= 1,
+                                    onclick: move |_| {
+                                        let profile = user_config.read().current_profile().clone();
+                                        let result = user_config.with_mut(|c| c.delete_profile(&profile.name));
+                                        if result.is_ok() {
+                                            spawn_secret_update(cx, secret_store, move |s| {
+                                                s.delete_password(&profile.name, &profile.tunnel_params.user_name)
+                                            });
+                                        }
+                                        profile_result(result);
+                                        load_password(user_config.read().current_profile().clone());
+                                    },
+                                    "Delete"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "New profile from an snx-rs config file",
+                                    onclick: move |_| open_profile_file(ProfileFile::Import),
+                                    "Import"
+                                }
+                                button {
+                                    class: "profile-button",
+                                    title: "Save profile as an snx-rs config file",
+                                    onclick: move |_| open_profile_file(ProfileFile::Export),
+                                    "Export"
+                                }
+                            }
+                        }
+                    }
                     div { class: "login-form",
                         input {
                             placeholder: "Username",
                             class: "form-input",
-                            value: "{username.read()}",
+                            value: "{config.user_name}",
                             oninput: move |e| {
-                                username.set(e.value.clone());
+                                user_config.with_mut(|c| c.current_params_mut().user_name = e.value.clone());
                             }
                         }
+                        FieldIssues { issues: form_issues(Field::UserName) }
                         input {
                             placeholder: "Password",
                             class: "form-input",
@@ -131,51 +979,23 @@ fn main() {
                                 password.set(e.value.clone());
                             }
                         }
+                        FieldIssues { issues: form_issues(Field::Password) }
                         div { class: "button-container",
                             // TODO trigger on enter
                             button {
                                 // TODO trigger on enter
                                 class: "form-button connect",
-                                disabled: if **connection_status { true } else { false },
+                                disabled: !connection_state.can_connect(),
                                 // TODO trigger on enter
                                 onclick: move |_| {
-                                    let username_string = username.read().to_string();
-                                    let password_string = password.read().to_string();
-                                    let server_address_string = server_address.read().to_string();
-                                    let mut error_state = false;
-                                    if username_string.is_empty() {
-                                        missing_username.set(true);
-                                        error_state = true;
-                                    } else {
-                                        missing_username.set(false);
-                                    }
-                                    if password_string.is_empty() {
-                                        missing_password.set(true);
-                                        error_state = true;
-                                    } else {
-                                        missing_password.set(false);
-                                    }
-                                    if server_address_string.is_empty() {
-                                        missing_server_address.set(true);
-                                        error_state = true;
-                                    } else {
-                                        missing_server_address.set(false);
-                                    }
-                                    if error_state {
-                                        return;
-                                    }
-                                    settings_expanded.set(false);
-                                    connect(current_settings());
-                                    password.set("".to_string());
+                                    connect();
                                 },
                                 "Connect"
                             }
                             button {
                                 class: "form-button disconnect",
-                                disabled: if **connection_status { false } else { true },
-                                onclick: move |_| {
-                                    disconnect();
-                                },
+                                disabled: !connection_state.can_disconnect(),
+                                onclick: move |_| disconnect(),
                                 "Disconnect"
                             }
                         }
@@ -183,34 +1003,215 @@ fn main() {
                     div { class: "remember-me",
                         input {
                             r#type: "checkbox",
-                            checked: if **remember_me { "true" } else { "false" },
+                            checked: if remember_me { "true" } else { "false" },
                             oninput: move |e| {
                                 let checked = match e.value.as_str() {
                                     "true" => true,
                                     "false" => false,
                                     _ => false,
                                 };
-                                save_config(UserConfig {
-                                        tunnel_params: current_settings(),
-                                        remember_me: checked,
-                                    })
-                                    .unwrap_or_else(|e| {
-                                        error!("Error: {}", e.to_string());
-                                    });
-                                remember_me.set(checked);
+                                user_config.with_mut(|c| c.remember_me = checked);
+                            }
+                        }
+                        span { "Remember user name" }
+                    }
+                    div { class: "remember-me",
+                        input {
+                            r#type: "checkbox",
+                            disabled: secret_store.is_none(),
+                            checked: if current_profile.save_password { "true" } else { "false" },
+                            oninput: move |e| {
+                                let checked = e.value.as_str() == "true";
+                                let profile = user_config.read().current_profile().clone();
+                                user_config.with_mut(|c| {
+                                    if let Some(p) = c.profiles.iter_mut().find(|p| p.name == profile.name) {
+                                        p.save_password = checked;
+                                    }
+                                });
+                                let password = password.read().to_string();
+                                spawn_secret_update(cx, secret_store, move |s| {
+                                    let user_name = &profile.tunnel_params.user_name;
+                                    if !checked {
+                                        s.delete_password(&profile.name, user_name)
+                                    } else if !password.is_empty() {
+                                        s.set_password(&profile.name, user_name, &password)
+                                    } else {
+                                        Ok(())
+                                    }
+                                });
                             }
                         }
-                        span { "Remember configuration" }
+                        span {
+                            title: if secret_store.is_none() { "Secret service is not available" } else { "" },
+                            "Save password"
+                        }
                     }
+                    command_error.get().as_ref().map(|(message, command)| {
+                        let command = command.clone();
+                        let action = match command {
+                            Command::Connect(_) | Command::ChallengeCode(..) => "Connect",
+                            Command::Disconnect => "Disconnect",
+                        };
+                        rsx! {
+                            div { class: "error-container error-banner",
+                                span { class: "error-text", "{action} failed: {message}" }
+                                button {
+                                    class: "banner-button",
+                                    onclick: move |_| {
+                                        let Ok(client) = service_client() else {
+                                            return;
+                                        };
+                                        run_command(cx, client, command.clone(), commands);
+                                    },
+                                    "Retry"
+                                }
+                                button {
+                                    class: "banner-button",
+                                    title: "Dismiss",
+                                    onclick: move |_| command_error.set(None),
+                                    "✕"
+                                }
+                            }
+                        }
+                    })
+                    presented.as_ref().map(|(mismatch, certificate)| {
+                        let (mismatch, fingerprint) = (*mismatch, certificate.fingerprint);
+                        let server = certificate.server.clone();
+                        let details = certificate.info.as_ref().map(ToString::to_string).unwrap_or_default();
+                        rsx! {
+                            div { class: if mismatch { "error-container pin-banner" } else { "pin-banner pin-offer" },
+                                if mismatch {
+                                    rsx! {
+                                        span { class: "pin-title",
+                                            "Warning: the certificate of {server} changed. Someone may be intercepting the connection, only trust the new certificate when your administrator replaced it."
+                                        }
+                                    }
+                                } else {
+                                    rsx! {
+                                        span { class: "pin-title",
+                                            "Pin the certificate of {server} to be warned before connecting when it changes"
+                                        }
+                                    }
+                                }
+                                span { class: "pin-fingerprint", "SHA-256 {fingerprint}" }
+                                span { class: "pin-details", "{details}" }
+                                div {
+                                    button {
+                                        class: "banner-button",
+                                        onclick: move |_| {
+                                            user_config.with_mut(|c| {
+                                                let params = c.current_params_mut();
+                                                if mismatch {
+                                                    params.pinned_fingerprints.clear();
+                                                }
+                                                pin_fingerprint(params, fingerprint);
+                                            });
+                                            server_check.set(None);
+                                        },
+                                        if mismatch { "Trust new certificate" } else { "Pin certificate" }
+                                    }
+                                    button {
+                                        class: "banner-button",
+                                        onclick: move |_| server_check.set(None),
+                                        "Dismiss"
+                                    }
+                                }
+                            }
+                        }
+                    })
                     div { class: "error-container",
-                        span { class: "error-text", display: if **missing_username { "block" } else { "none" }, "Error: Username is required" }
-                        span { class: "error-text", display: if **missing_password { "block" } else { "none" }, "Error: Password is required" }
-                        span { class: "error-text", display: if **missing_server_address { "block" } else { "none" }, "Error: Server adress is required" }
+                        (**validation_shown && setting_errors).then(|| rsx! {
+                            span { class: "error-text", "Error: Fix the settings marked in the settings panel" }
+                        })
+                        service_address_error.as_ref().map(|e| rsx! {
+                            span { class: "error-text", "Error: {e}" }
+                        })
+                        profile_error.get().as_ref().map(|e| rsx! {
+                            span { class: "error-text", "Error: {e}" }
+                        })
+                        import_report.get().iter().map(|skipped| rsx! {
+                            span { class: "error-text", "Not imported, {skipped}" }
+                        })
+                        (!unsupported_options.is_empty()).then(|| rsx! {
+                            span { class: "error-text",
+                                "Warning: the {protocol} service protocol does not support {unsupported_options}"
+                            }
+                        })
                     }
                     div { class: "status",
                         span { class: "status-text", connection_status_msg.to_string() }
-                        span { class: if **connection_status { "status-text-green" } else { "status-text-red" },
-                            if **connection_status{ "connected" } else { "disconected" }
+                        span {
+                            class: if connection_state.is_connected() {
+                                "status-text-green"
+                            } else if connection_state.is_busy() {
+                                "status-text-yellow"
+                            } else {
+                                "status-text-red"
+                            },
+                            "{connection_state}"
+                        }
+                        if connection_state.is_busy() {
+                            rsx! { span { class: "spinner" } }
+                        }
+                        match reconnect.get() {
+                            Reconnect::Waiting { at, attempt } => rsx! {
+                                div { class: "status-details",
+                                    ReconnectCountdown {
+                                        at: *at,
+                                        attempt: *attempt,
+                                        max_attempts: user_config.read().reconnect.max_attempts,
+                                    }
+                                    button {
+                                        class: "banner-button",
+                                        onclick: move |_| reconnect.set(Reconnect::Idle),
+                                        "Cancel"
+                                    }
+                                }
+                            },
+                            Reconnect::Connecting { attempt } => rsx! {
+                                div { class: "status-details",
+                                    span { class: "status-text", "Reconnect attempt {attempt}" }
+                                }
+                            },
+                            Reconnect::Idle => rsx! { "" },
+                        }
+                        connected_since.map(|since| {
+                            let since_text = since.format("%Y-%m-%d %H:%M:%S").to_string();
+                            rsx! {
+                                div { class: "status-details",
+                                    span { class: "status-text", "Connected since: {since_text}" }
+                                    br {}
+                                    span { class: "status-text", "Session duration: " }
+                                    SessionDuration { since: since }
+                                }
+                            }
+                        })
+                        div { class: "status-details", display: if connection_state.is_connected() { "block" } else { "none" },
+                            active_server.map(|server| {
+                                let tunnel = active_tunnel_type.map(|t| format!(" ({})", t)).unwrap_or_default();
+                                rsx! {
+                                    span { class: "status-text", "Server: {server}{tunnel}" }
+                                    br {}
+                                }
+                            })
+                            details.ip_address.map(|ip| rsx! {
+                                span { class: "status-text", "IP address: {ip}" }
+                                br {}
+                            })
+                            details.dns_servers.filter(|d| !d.is_empty()).map(|dns| {
+                                let dns = dns.join(", ");
+                                rsx! {
+                                    span { class: "status-text", "DNS servers: {dns}" }
+                                    br {}
+                                }
+                            })
+                            details.routes.filter(|r| !r.is_empty()).map(|routes| {
+                                let routes = routes.join(", ");
+                                rsx! {
+                                    span { class: "status-text", "Routes: {routes}" }
+                                    br {}
+                                }
+                            })
                         }
                         br {}
                         span { class: "status-text", status_msg.to_string() }
@@ -218,19 +1219,59 @@ fn main() {
                             if **status { "running" } else { "stopped" }
                         }
                     }
-                    // div {
-                    //     class: "restart",
-                    //     button {
-                    //         class: "restart-button",
-                    //         onclick: move |_| {
-                    //             info!("Restarting snx-rs service");
-                    //             let status = Command::new("pkexec").arg("--askpass").arg("systemctl").arg("restart").arg("snx-rs").status().unwrap();
-                    //             info!("Status: {}", status); 
-                    //         },
-                    //         "Restart service"
-                    //     }
-                    // }
+                    ServicePanel { window_visible: **window_visible }
                 }
+                challenge.get().as_ref().map(|c| {
+                    let params = c.params.clone();
+                    rsx! {
+                        div { class: "modal-overlay",
+                            div { class: "modal",
+                                h3 { "Multi-factor authentication" }
+                                span { class: "modal-prompt", "{c.prompt}" }
+                                input {
+                                    placeholder: "Code",
+                                    class: "form-input",
+                                    autofocus: true,
+                                    autocomplete: "one-time-code",
+                                    value: "{challenge_code.read()}",
+                                    oninput: move |e| challenge_code.set(e.value.clone())
+                                }
+                                div { class: "button-container",
+                                    button {
+                                        class: "form-button connect",
+                                        disabled: challenge_code.read().trim().is_empty(),
+                                        onclick: move |_| {
+                                            let Ok(client) = service_client() else {
+                                                return;
+                                            };
+                                            let code = challenge_code.read().trim().to_string();
+                                            challenge_code.set(String::new());
+                                            run_command(cx, client, Command::ChallengeCode(code, params.clone()), commands);
+                                        },
+                                        "Submit"
+                                    }
+                                    button {
+                                        class: "form-button disconnect",
+                                        onclick: move |_| {
+                                            challenge_code.set(String::new());
+                                            commands.cancel_connect();
+                                            // Let the service drop the half-finished login.
+                                            let Ok(client) = service_client() else {
+                                                return;
+                                            };
+                                            cx.spawn(async move {
+                                                if let Err(e) = client.disconnect().await {
+                                                    error!("Cannot cancel login: {}", e);
+                                                }
+                                            });
+                                        },
+                                        "Cancel"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                })
                 img {
                     src: "../lib/snx-rs-gui/assets/settings_white.png",
                     class: "settings-icon",
@@ -239,23 +1280,55 @@ fn main() {
                     }
                 }
                 div { class: "settings-panel", display: if **settings_expanded { "block" } else { "none" },
-                    h3 { "Settings" }
+                    h3 { "Settings: {current_profile.name}" }
                     ul {
+                        li {
+                            "Service address"
+                            input {
+                                placeholder: "127.0.0.1:7779",
+                                class: "settings-form-input",
+                                disabled: service_address_override.is_some(),
+                                title: if service_address_override.is_some() { "Set from the command line or environment" } else { "" },
+                                value: "{service_address_value}",
+                                oninput: move |e| {
+                                    user_config.with_mut(|c| c.service_address = e.value.clone());
+                                }
+                            }
+                        }
+                        li {
+                            "Service protocol"
+                            select {
+                                value: "{protocol_value}",
+                                onchange: move |selection| {
+                                    let protocol = Protocol::ALL
+                                        .into_iter()
+                                        .find(|p| p.to_string() == selection.data.value);
+                                    user_config.with_mut(|c| c.protocol = protocol);
+                                },
+                                option { value: "detect", "{detect_label}" }
+                                Protocol::ALL.iter().map(|protocol| rsx! {
+                                    option { value: "{protocol}", "{protocol}" }
+                                })
+                            }
+                        }
                         li {
                             "Server adress"
                             input {
                                 placeholder: "",
                                 class: "settings-form-input",
-                                value: "{server_address.read()}",
-                                oninput: move |e| { server_address.set(e.value.clone()) }
+                                value: "{config.server_name}",
+                                oninput: move |e| {
+                                    user_config.with_mut(|c| c.current_params_mut().server_name = e.value.clone());
+                                }
                             }
                         }
+                        FieldIssues { issues: issues(Field::ServerName) }
                         li {
                             "Log level"
                             select {
-                                value: "{log_level.read()}",
+                                value: "{config.log_level}",
                                 onchange: move |selection| {
-                                    log_level.set(selection.data.value.clone());
+                                    user_config.with_mut(|c| c.current_params_mut().log_level = selection.data.value.clone());
                                 },
                                 option { "debug" }
                                 option { "info" }
@@ -267,50 +1340,99 @@ fn main() {
                             "Reauthorization"
                             input {
                                 r#type: "checkbox",
-                                checked: if **reauth { "true" } else { "false" },
+                                checked: if config.reauth { "true" } else { "false" },
                                 oninput: move |e| {
-                                    match e.value.as_str() {
-                                        "true" => reauth.set(true),
-                                        "false" => reauth.set(false),
-                                        _ => reauth.set(false),
-                                    }
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.current_params_mut().reauth = checked);
                                 }
                             }
                         }
+                        li { "Search domains" }
                         li {
-                            "Search domains"
+                            class: "domain-chips",
+                            search_domains.iter().enumerate().map(|(index, domain)| {
+                                let last = index + 1 == search_domains.len();
+                                rsx! {
+                                    span {
+                                        key: "{domain}",
+                                        class: "domain-chip",
+                                        button {
+                                            class: "chip-button",
+                                            title: "Try earlier",
+                                            disabled: index == 0,
+                                            onclick: move |_| {
+                                                user_config.with_mut(|c| c.current_params_mut().move_search_domain(index, index - 1));
+                                            },
+                                            "‹"
+                                        }
+                                        "{domain}"
+                                        button {
+                                            class: "chip-button",
+                                            title: "Try later",
+                                            disabled: last,
+                                            onclick: move |_| {
+                                                user_config.with_mut(|c| c.current_params_mut().move_search_domain(index, index + 1));
+                                            },
+                                            "›"
+                                        }
+                                        button {
+                                            class: "chip-button",
+                                            title: "Remove search domain",
+                                            onclick: move |_| {
+                                                user_config.with_mut(|c| {
+                                                    c.current_params_mut().search_domains.remove(index);
+                                                });
+                                            },
+                                            "✕"
+                                        }
+                                    }
+                                }
+                            })
+                        }
+                        li {
+                            class: "domain-add",
                             input {
-                                value: "{search_domains.read()}",
-                                placeholder: "",
+                                placeholder: "example.com",
                                 class: "settings-form-input",
-                                oninput: move |e| { search_domains.set(e.value.clone()) }
+                                value: "{domain_input.read()}",
+                                oninput: move |e| { domain_input.set(e.value.clone()) },
+                                onkeydown: move |e| {
+                                    if e.key() == Key::Enter {
+                                        add_search_domains();
+                                    }
+                                }
+                            }
+                            button {
+                                class: "domain-button",
+                                disabled: domain_input.read().trim().is_empty(),
+                                onclick: move |_| add_search_domains(),
+                                "Add"
                             }
                         }
+                        domain_error.get().as_ref().map(|e| rsx! {
+                            span { class: "field-error", "{e}" }
+                        })
+                        FieldIssues { issues: issues(Field::SearchDomains) }
                         li {
                             "Default route"
                             input {
                                 r#type: "checkbox",
-                                checked: if **default_route { "true" } else { "false" },
+                                checked: if config.default_route { "true" } else { "false" },
                                 oninput: move |e| {
-                                    match e.value.as_str() {
-                                        "true" => default_route.set(true),
-                                        "false" => default_route.set(false),
-                                        _ => default_route.set(false),
-                                    }
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.current_params_mut().default_route = checked);
                                 }
                             }
                         }
+                        FieldIssues { issues: issues(Field::DefaultRoute) }
                         li {
                             "No routing"
                             input {
                                 r#type: "checkbox",
-                                checked: if **no_routing { "true" } else { "false" },
+                                checked: if config.no_routing { "true" } else { "false" },
                                 oninput: move |e| {
-                                    match e.value.as_str() {
-                                        "true" => no_routing.set(true),
-                                        "false" => no_routing.set(false),
-                                        _ => no_routing.set(false),
-                                    }
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.current_params_mut().no_routing = checked);
                                 }
                             }
                         }
@@ -318,13 +1440,10 @@ fn main() {
                             "No DNS"
                             input {
                                 r#type: "checkbox",
-                                checked: if **no_dns { "true" } else { "false" },
+                                checked: if config.no_dns { "true" } else { "false" },
                                 oninput: move |e| {
-                                    match e.value.as_str() {
-                                        "true" => no_dns.set(true),
-                                        "false" => no_dns.set(false),
-                                        _ => no_dns.set(false),
-                                    }
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.current_params_mut().no_dns = checked);
                                 }
                             }
                         }
@@ -332,21 +1451,88 @@ fn main() {
                             "No cert check"
                             input {
                                 r#type: "checkbox",
-                                checked: if **no_cert_check { "true" } else { "false" },
+                                checked: if config.no_cert_check { "true" } else { "false" },
                                 oninput: move |e| {
-                                    match e.value.as_str() {
-                                        "true" => no_cert_check.set(true),
-                                        "false" => no_cert_check.set(false),
-                                        _ => no_cert_check.set(false),
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.current_params_mut().no_cert_check = checked);
+                                }
+                            }
+                        }
+                        FieldIssues { issues: issues(Field::NoCertCheck) }
+                        li { "Pinned server certificates" }
+                        pins.iter().map(|fingerprint| {
+                            let fingerprint = *fingerprint;
+                            rsx! {
+                                li {
+                                    key: "{fingerprint}",
+                                    class: "pinned-certificate",
+                                    span { class: "pin-fingerprint", "{fingerprint}" }
+                                    button {
+                                        class: "pin-button",
+                                        title: "Remove pinned certificate",
+                                        onclick: move |_| {
+                                            user_config.with_mut(|c| {
+                                                c.current_params_mut().pinned_fingerprints.retain(|pin| *pin != fingerprint);
+                                            });
+                                        },
+                                        "Remove"
                                     }
                                 }
                             }
+                        })
+                        li {
+                            class: "pin-add",
+                            input {
+                                placeholder: "SHA-256 fingerprint",
+                                class: "settings-form-input",
+                                value: "{pin_input.read()}",
+                                oninput: move |e| { pin_input.set(e.value.clone()) }
+                            }
+                            button {
+                                class: "pin-button",
+                                disabled: pin_input.read().trim().is_empty(),
+                                onclick: move |_| {
+                                    let parsed = pin_input.read().parse::<Fingerprint>();
+                                    match parsed {
+                                        Ok(fingerprint) => {
+                                            user_config.with_mut(|c| pin_fingerprint(c.current_params_mut(), fingerprint));
+                                            pin_input.set(String::new());
+                                            pin_error.set(None);
+                                        }
+                                        Err(e) => pin_error.set(Some(e.to_string())),
+                                    }
+                                },
+                                "Pin"
+                            }
+                            button {
+                                class: "pin-button",
+                                title: "Show the certificate the server presents",
+                                disabled: config.server_name.trim().is_empty(),
+                                onclick: move |_| {
+                                    let params = current_settings();
+                                    let server_check = server_check.to_owned();
+                                    let pin_error = pin_error.to_owned();
+                                    cx.spawn(async move {
+                                        match check_server(&params).await {
+                                            Ok(check) => {
+                                                pin_error.set(None);
+                                                server_check.set(Some(check));
+                                            }
+                                            Err(e) => pin_error.set(Some(format!("Cannot fetch the certificate: {}", e))),
+                                        }
+                                    });
+                                },
+                                "Fetch"
+                            }
                         }
+                        pin_error.get().as_ref().map(|e| rsx! {
+                            span { class: "field-error", "{e}" }
+                        })
                         li {
                             "Tunnel type"
                             select {
                                 value: {
-    match tunnel_type.read().to_owned() {
+    match config.tunnel_type {
         TunnelType::Ssl => "SSL",
         TunnelType::Ipsec => "IPSec",
     }
@@ -357,27 +1543,84 @@ fn main() {
                                         "IPSec" => TunnelType::Ipsec,
                                         _ => TunnelType::Ssl,
                                     };
-                                    tunnel_type.set(tunnel);
+                                    user_config.with_mut(|c| c.current_params_mut().tunnel_type = tunnel);
                                 },
                                 option { TunnelType::Ssl.to_string() }
                                 option { TunnelType::Ipsec.to_string() }
                             }
                         }
+                        li { "CA certificates" }
+                        ca_certs.iter().map(|(index, path, details)| {
+                            let index = *index;
+                            rsx! {
+                                li {
+                                    key: "{index}",
+                                    class: "ca-cert",
+                                    div {
+                                        span { class: "ca-cert-path", "{path}" }
+                                        details.iter().map(|detail| rsx! {
+                                            span { class: "ca-cert-details", "{detail}" }
+                                        })
+                                    }
+                                    button {
+                                        class: "ca-cert-button",
+                                        title: "Remove CA certificate",
+                                        onclick: move |_| {
+                                            user_config.with_mut(|c| {
+                                                c.current_params_mut().ca_cert.remove(index);
+                                            });
+                                        },
+                                        "Remove"
+                                    }
+                                }
+                            }
+                        })
                         li {
-                            "CA cert path"
+                            class: "ca-cert-add",
                             input {
                                 placeholder: "path",
                                 class: "settings-form-input",
-                                // TODO bound to model
-                                value: "",
-                                oninput: move |e| { ca_cert.set(Some(PathBuf::from(e.value.clone()))) }
+                                value: "{ca_cert_path.read()}",
+                                oninput: move |e| { ca_cert_path.set(e.value.clone()) }
+                            }
+                            button {
+                                class: "ca-cert-button",
+                                disabled: ca_cert_path.read().trim().is_empty(),
+                                onclick: move |_| {
+                                    let path = PathBuf::from(ca_cert_path.read().trim());
+                                    user_config.with_mut(|c| add_ca_certs(c.current_params_mut(), vec![path]));
+                                    ca_cert_path.set(String::new());
+                                },
+                                "Add"
+                            }
+                            button {
+                                class: "ca-cert-button",
+                                title: "Choose CA certificate files",
+                                onclick: move |_| {
+                                    let user_config = user_config.to_owned();
+                                    let ca_cert_error = ca_cert_error.to_owned();
+                                    cx.spawn(async move {
+                                        match open_files("CA certificates", true, &CERTIFICATE_FILTER).await {
+                                            Ok(paths) => {
+                                                ca_cert_error.set(None);
+                                                user_config.with_mut(|c| add_ca_certs(c.current_params_mut(), paths));
+                                            }
+                                            Err(e) => ca_cert_error.set(Some(e.to_string())),
+                                        }
+                                    });
+                                },
+                                "Browse…"
                             }
                         }
+                        ca_cert_error.get().as_ref().map(|e| rsx! {
+                            span { class: "field-error", "{e}" }
+                        })
+                        FieldIssues { issues: issues(Field::CaCert) }
                         li {
                             "Login type"
                             select {
                                 value: {
-    match login_type.read().to_owned() {
+    match config.login_type {
         model::LoginType::Password => "Password",
         model::LoginType::PasswordWithMfa => "Password with MFA",
         model::LoginType::PasswordWithMsAuth => "Password with MS auth",
@@ -386,17 +1629,15 @@ fn main() {
     }
 },
                                 onchange: move |selection| {
-                                    login_type
-                                        .set(
-                                            match selection.data.value.clone().as_str() {
-                                                "Password" => model::LoginType::Password,
-                                                "Password with MFA" => model::LoginType::PasswordWithMfa,
-                                                "Password with MS auth" => model::LoginType::PasswordWithMsAuth,
-                                                "Emergency access" => model::LoginType::EmergencyAccess,
-                                                "SSO Azure" => model::LoginType::SsoAzure,
-                                                _ => model::LoginType::Password,
-                                            },
-                                        );
+                                    let login_type = match selection.data.value.clone().as_str() {
+                                        "Password" => model::LoginType::Password,
+                                        "Password with MFA" => model::LoginType::PasswordWithMfa,
+                                        "Password with MS auth" => model::LoginType::PasswordWithMsAuth,
+                                        "Emergency access" => model::LoginType::EmergencyAccess,
+                                        "SSO Azure" => model::LoginType::SsoAzure,
+                                        _ => model::LoginType::Password,
+                                    };
+                                    user_config.with_mut(|c| c.current_params_mut().login_type = login_type);
                                 },
                                 option { "Password" }
                                 option { "Password with MFA" }
@@ -406,196 +1647,503 @@ fn main() {
                             }
                         }
                     }
+                    h3 { "Notifications" }
+                    ul {
+                        li {
+                            "Tunnel up"
+                            input {
+                                r#type: "checkbox",
+                                checked: if notifications.tunnel_up { "true" } else { "false" },
+                                oninput: move |e| {
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.notifications.tunnel_up = checked);
+                                }
+                            }
+                        }
+                        li {
+                            "Unexpected disconnect"
+                            input {
+                                r#type: "checkbox",
+                                checked: if notifications.tunnel_dropped { "true" } else { "false" },
+                                oninput: move |e| {
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.notifications.tunnel_dropped = checked);
+                                }
+                            }
+                        }
+                        li {
+                            "Service stopped"
+                            input {
+                                r#type: "checkbox",
+                                checked: if notifications.service_stopped { "true" } else { "false" },
+                                oninput: move |e| {
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.notifications.service_stopped = checked);
+                                }
+                            }
+                        }
+                    }
+                    h3 { "Auto-reconnect" }
+                    ul {
+                        li {
+                            "Reconnect after unexpected disconnects"
+                            input {
+                                r#type: "checkbox",
+                                title: "Needs a saved password",
+                                checked: if reconnect_settings.enabled { "true" } else { "false" },
+                                oninput: move |e| {
+                                    let checked = e.value.as_str() == "true";
+                                    user_config.with_mut(|c| c.reconnect.enabled = checked);
+                                }
+                            }
+                        }
+                        li {
+                            "Max attempts"
+                            input {
+                                r#type: "number",
+                                min: "1",
+                                class: "settings-form-input",
+                                value: "{reconnect_settings.max_attempts}",
+                                oninput: move |e| {
+                                    if let Ok(attempts) = e.value.parse::<u32>() {
+                                        user_config.with_mut(|c| c.reconnect.max_attempts = attempts.max(1));
+                                    }
+                                }
+                            }
+                        }
+                        li {
+                            "First delay (seconds)"
+                            input {
+                                r#type: "number",
+                                min: "1",
+                                class: "settings-form-input",
+                                value: "{reconnect_settings.initial_delay_secs}",
+                                oninput: move |e| {
+                                    if let Ok(delay) = e.value.parse::<u64>() {
+                                        user_config.with_mut(|c| c.reconnect.initial_delay_secs = delay.max(1));
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
         })
     }
 
-    fn status_service(
-        cx: Scope<UserConfig>,
-        status: &UseState<bool>,
-        connection_status: &UseState<bool>,
-    ) {
-        use_coroutine(cx, |_rx: UnboundedReceiver<bool>| {
-            info!("Status service coroutine called");
-            let sync_status = status.to_owned();
-            let connection_sync_status = connection_status.to_owned();
+    fn pin_fingerprint(params: &mut TunnelParams, fingerprint: Fingerprint) {
+        if !params.pinned_fingerprints.contains(&fingerprint) {
+            params.pinned_fingerprints.push(fingerprint);
+        }
+    }
+
+    /// Appends the CA files not in the list yet.
+    fn add_ca_certs(params: &mut TunnelParams, paths: Vec<PathBuf>) {
+        for path in paths {
+            if !params.ca_cert.contains(&path) {
+                params.ca_cert.push(path);
+            }
+        }
+    }
+
+    /// Inline errors and warnings below a setting.
+    #[inline_props]
+    fn FieldIssues(cx: Scope, issues: Vec<Issue>) -> Element {
+        cx.render(rsx! {
+            issues.iter().map(|issue| rsx! {
+                span {
+                    class: if issue.severity == Severity::Error { "field-error" } else { "field-warning" },
+                    "{issue}"
+                }
+            })
+        })
+    }
+
+    /// Live session duration, re-rendered every second on its own.
+    #[inline_props]
+    fn SessionDuration(cx: Scope, since: DateTime<Local>) -> Element {
+        let now = use_state(cx, Local::now);
+        use_future(cx, (), |_| {
+            let now = now.to_owned();
             async move {
                 loop {
-                    let socket_opt = create_client_socket();
-                    socket_opt
-                        .map(|socket| get_status(socket))
-                        .map(|r| match r {
-                            Err(e) => {
-                                error!("error {}", e.to_string().as_str());
-                                sync_status.set(false);
-                                connection_sync_status.set(false);
-                            }
-                            Ok(connected) => {
-                                connection_sync_status.set(connected);
-                                sync_status.set(true);
-                            }
-                        });
-                    sleep(Duration::from_secs(5)).await;
+                    sleep(Duration::from_secs(1)).await;
+                    now.set(Local::now());
                 }
             }
         });
+        let duration = format_duration((**now - *since).max(chrono::Duration::zero()));
+        cx.render(rsx! {
+            span { class: "status-text", "{duration}" }
+        })
     }
 
-    fn get_status(socket: UdpSocket) -> Result<bool, std::io::Error> {
-        info!("Getting status");
-        let _request = socket.send("\"GetStatus\"".to_string().as_bytes());
-        let response_result = handle_response(socket);
-        let response = match response_result {
-            Ok(r) => r,
-            Err(e) => return Err(e),
-        };
-        // TODO add parsing of the response
-        let response_object: model::TunnelServiceResponse = match serde_json::from_str(&response) {
-            Ok(r) => r,
-            Err(_) => {
-                return Err(std::io::Error::new(
-                    ErrorKind::InvalidData,
-                    "cannot parse connection status".to_string(),
-                ));
-            }
-        };
-        match response_object {
-            model::TunnelServiceResponse::ConnectionStatus(status) => {
-                info!("Connection status: {:?}", status);
-                return Ok(status.connected_since.is_some());
+    /// Countdown to the next reconnect attempt, re-rendered every second on its own.
+    #[inline_props]
+    fn ReconnectCountdown(
+        cx: Scope,
+        at: DateTime<Local>,
+        attempt: u32,
+        max_attempts: u32,
+    ) -> Element {
+        let now = use_state(cx, Local::now);
+        use_future(cx, (), |_| {
+            let now = now.to_owned();
+            async move {
+                loop {
+                    sleep(Duration::from_secs(1)).await;
+                    now.set(Local::now());
+                }
             }
-            model::TunnelServiceResponse::Ok => {
-                info!("Connection status: Ok");
-                return Ok(true);
+        });
+        let seconds = (*at - **now).num_seconds().max(0);
+        cx.render(rsx! {
+            span { class: "status-text", "Reconnecting in {seconds}s (attempt {attempt} of {max_attempts})" }
+        })
+    }
+
+    /// State and journal of the snx-rs systemd unit, with start, stop and restart authorized by polkit.
+    ///
+    /// systemd is only asked while the panel is expanded and the window is shown.
+    #[inline_props]
+    fn ServicePanel(cx: Scope, window_visible: bool) -> Element {
+        let expanded = use_state(cx, || false);
+        let polling = **expanded && *window_visible;
+        let service = use_ref(cx, || None::<SystemdService>);
+        let unit_state = use_state(cx, || None::<Result<UnitState, String>>);
+        let journal_lines = use_state(cx, Vec::<String>::new);
+        let action_error = use_state(cx, || None::<String>);
+        let busy = use_state(cx, || false);
+        // Bumped after every action to reload the journal along with the state.
+        let revision = use_state(cx, || 0u32);
+
+        use_future(cx, (revision.get(), &polling), |(_, polling)| {
+            let service = service.to_owned();
+            let unit_state = unit_state.to_owned();
+            let journal_lines = journal_lines.to_owned();
+            async move {
+                if !polling {
+                    return;
+                }
+                match journal(SNX_RS_UNIT, JOURNAL_LINES).await {
+                    Ok(lines) => journal_lines.set(lines),
+                    Err(e) => journal_lines.set(vec![e.to_string()]),
+                }
+                loop {
+                    let state = match systemd_service(&service).await {
+                        Ok(s) => s.state().await,
+                        Err(e) => Err(e),
+                    };
+                    unit_state.set(Some(state.map_err(|e| e.to_string())));
+                    sleep(Duration::from_secs(5)).await;
+                }
             }
-            model::TunnelServiceResponse::Error(error) => {
-                error!("Connection status: Error {:?}", error);
-                return Ok(false);
+        });
+
+        let run_action = move |action: UnitAction| {
+            let service = service.to_owned();
+            let action_error = action_error.to_owned();
+            let busy = busy.to_owned();
+            let revision = revision.to_owned();
+            busy.set(true);
+            cx.spawn(async move {
+                let result = match systemd_service(&service).await {
+                    Ok(s) => s.run(action).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(()) => action_error.set(None),
+                    Err(e) => {
+                        error!("Cannot {} {}: {}", action, SNX_RS_UNIT, e);
+                        action_error.set(Some(format!("Cannot {} the service: {}", action, e)));
+                    }
+                }
+                busy.set(false);
+                revision.modify(|r| r + 1);
+            });
+        };
+
+        let state = unit_state.get().as_ref().and_then(|s| s.as_ref().ok());
+        let installed = state.is_some_and(|s| s.is_installed());
+        let active = state.is_some_and(|s| s.is_active());
+        let state_text = match unit_state.get() {
+            Some(Ok(state)) => state.to_string(),
+            Some(Err(e)) => format!("unknown ({})", e),
+            None => "…".to_string(),
+        };
+        let since = state
+            .and_then(|s| s.active_since())
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+        let log = journal_lines.join("\n");
+
+        cx.render(rsx! {
+            details { class: "service-panel",
+                ontoggle: move |_| expanded.modify(|e| !e),
+                summary { "Manage service" }
+                span { class: "status-text", "Unit {SNX_RS_UNIT}: " }
+                span { class: if active { "status-text-green" } else { "status-text-red" }, "{state_text}" }
+                since.map(|since| rsx! {
+                    br {}
+                    span { class: "status-text", "Active since: {since}" }
+                })
+                div { class: "button-container",
+                    button {
+                        class: "form-button",
+                        disabled: **busy || !installed || active,
+                        onclick: move |_| run_action(UnitAction::Start),
+                        "Start"
+                    }
+                    button {
+                        class: "form-button",
+                        disabled: **busy || !active,
+                        onclick: move |_| run_action(UnitAction::Stop),
+                        "Stop"
+                    }
+                    button {
+                        class: "form-button",
+                        disabled: **busy || !installed,
+                        onclick: move |_| run_action(UnitAction::Restart),
+                        "Restart"
+                    }
+                }
+                action_error.get().as_ref().map(|e| rsx! {
+                    span { class: "error-text", "Error: {e}" }
+                })
+                pre { class: "journal", "{log}" }
             }
-        }
+        })
     }
 
-    fn handle_response(socket: UdpSocket) -> Result<String, std::io::Error> {
-        info!("Handling response");
-        let mut buf = vec![0u8; MAX_PACKET_SIZE];
-        let response = socket.recv_from(&mut buf);
-        if response.is_err() || response.as_ref().unwrap().0 <= 0 {
-            error!("Response not received");
-            return Err(std::io::Error::new(
-                ErrorKind::NotConnected,
-                "Response not received",
-            ));
+    /// Connects to systemd on first use.
+    async fn systemd_service(
+        cache: &UseRef<Option<SystemdService>>,
+    ) -> Result<SystemdService, Error> {
+        if let Some(service) = cache.read().clone() {
+            return Ok(service);
         }
-        info!("received data: {}", response.as_ref().unwrap().0);
-        // TODO add commands models
-        let response_string =
-            match String::from_utf8(buf.iter().cloned().filter(|&b| b != 0).collect()) {
-                Ok(s) => s,
-                Err(e) => format!("Invalid UTF-8 sequence: {}", e),
+        let service = SystemdService::system(SNX_RS_UNIT).await?;
+        *cache.write_silent() = Some(service.clone());
+        Ok(service)
+    }
+
+    fn status_service(
+        cx: Scope<AppProps>,
+        user_config: &UseRef<UserConfig>,
+        resolved_address: &UseRef<Option<Result<SocketAddr, String>>>,
+        status: &UseState<bool>,
+        connection_state: &UseState<ConnectionState>,
+        details: &UseState<Option<ConnectionStatus>>,
+        detected_protocol: &UseState<Option<Protocol>>,
+        subscription: &StatusSubscription,
+    ) {
+        use_coroutine(cx, |_rx: UnboundedReceiver<bool>| {
+            info!("Status service coroutine called");
+            let sync_status = status.to_owned();
+            let connection_sync_state = connection_state.to_owned();
+            let connection_sync_details = details.to_owned();
+            let detected_protocol = detected_protocol.to_owned();
+            let user_config = user_config.to_owned();
+            let subscription = subscription.clone();
+            let source = {
+                let resolved_address = resolved_address.to_owned();
+                PollingSource::new(move || match &*resolved_address.read() {
+                    Some(Ok(address)) => Ok(*address),
+                    Some(Err(e)) => Err(Error::new(ErrorKind::InvalidInput, e.clone())),
+                    None => Err(Error::new(
+                        ErrorKind::WouldBlock,
+                        "The service address is not resolved yet",
+                    )),
+                })
             };
-        info!("Response: {}", response_string);
-        Ok(response_string)
+            // The state found on start is not news.
+            let mut first_poll = true;
+            let on_update = move |response: Result<TunnelServiceResponse, Error>| {
+                // Read after the poll, a command may have moved the state meanwhile.
+                let previous = (*connection_sync_state.current()).clone();
+                let was_running = *sync_status.current();
+                let (next, running, server) = match response {
+                    Err(e) => {
+                        error!("error {}", e.to_string().as_str());
+                        let next = previous.next(&ConnectionEvent::ServiceDown);
+                        sync_status.set(false);
+                        connection_sync_state.set(next.clone());
+                        connection_sync_details.set(None);
+                        (next, false, None)
+                    }
+                    Ok(response) => {
+                        if let TunnelServiceResponse::ConnectionStatus(status) = &response {
+                            let detected = Protocol::detect(status);
+                            if detected.is_some() && detected != *detected_protocol.current() {
+                                info!(
+                                    "Service speaks the {} protocol",
+                                    detected.unwrap_or_default()
+                                );
+                                detected_protocol.set(detected);
+                            }
+                        }
+                        let details = connection_details(response);
+                        let next = previous.next(&ConnectionEvent::StatusPolled(details.is_some()));
+                        let server = details.as_ref().and_then(|d| d.server_name.clone());
+                        connection_sync_state.set(next.clone());
+                        connection_sync_details.set(details);
+                        sync_status.set(true);
+                        (next, true, server)
+                    }
+                };
+                if !first_poll {
+                    if let Some(notification) = ConnectionNotification::for_change(
+                        &previous,
+                        &next,
+                        was_running,
+                        running,
+                        server.as_deref(),
+                    ) {
+                        notify(notification, &user_config.read().notifications);
+                    }
+                }
+                first_poll = false;
+            };
+            async move {
+                watch_status(source, PollPolicy::default(), &subscription, on_update).await;
+            }
+        });
     }
 
-    fn create_client_socket() -> Option<UdpSocket> {
-        info!("Creating client socket");
-        let socket = UdpSocket::bind("127.0.0.1:0").expect("couldn't bind to address");
-        info!("Connecting to address {}", SERVER_ADDRESS);
-        socket
-            .connect(SERVER_ADDRESS)
-            .expect("could not connect to adress");
-        socket
-            .set_read_timeout(Some(Duration::from_millis(200)))
-            .expect("could not set timeout");
-        socket
-            .set_write_timeout(Some(Duration::from_millis(200)))
-            .expect("could not set timeout");
-        return Some(socket);
+    /// Sends `command` in the background and moves the connection state along,
+    /// keeping the command in `command_error` when it fails.
+    fn run_command(cx: &ScopeState, client: TunnelClient, command: Command, state: &CommandState) {
+        let state = state.clone();
+        state.start(&command);
+        cx.spawn(state.run(client, command));
     }
 
-    fn connect(params: TunnelParams) {
-        info!("Connecting user to server...");
-        let params_json = serde_json::to_string(&params).unwrap();
-        let socket_opt = create_client_socket();
-        let socket = match socket_opt {
-            Some(s) => s,
-            None => return,
+    /// Runs `--connect`, `--disconnect` or `--status` without starting the GUI and returns the exit code.
+    fn run_headless(
+        action: Action,
+        args: &CliArgs,
+        service_address_override: Option<String>,
+    ) -> i32 {
+        let config = read_config().unwrap_or_default();
+        if matches!(action, Action::Import | Action::Export) {
+            return run_profile_file(action, args, config);
+        }
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return 1;
+            }
         };
-        let _request = socket.send(format!("{{\"Connect\": {}}}", params_json).as_bytes());
-        let response = handle_response(socket);
-        match response {
-            Ok(r) => {
-                info!("Response: {}", r);
+        let address = service_address_override.unwrap_or_else(|| config.service_address.clone());
+        let client = match runtime.block_on(resolve_service_address(&address)) {
+            Ok(address) => {
+                TunnelClient::new(address).with_protocol(config.protocol.unwrap_or_default())
             }
             Err(e) => {
-                error!("Error: {}", e.to_string());
+                eprintln!("Error: {}", e);
+                return 2;
+            }
+        };
+        let result: Result<bool, Error> = runtime.block_on(async {
+            match action {
+                Action::Status => {
+                    let report = headless::status(&client).await;
+                    match args.json {
+                        true => println!("{}", serde_json::to_string(&report)?),
+                        false => println!("{}", report),
+                    }
+                    Ok(report.service_running)
+                }
+                Action::Disconnect => {
+                    headless::disconnect(&client).await?;
+                    println!("Disconnected");
+                    Ok(true)
+                }
+                Action::Import | Action::Export => unreachable!("handled by run_profile_file"),
+                Action::Connect => {
+                    let profile = profiles::select_profile(&config, args.profile.as_deref())?;
+                    let password =
+                        headless::connect_password(profile, default_secret_store()).await?;
+                    let report = headless::connect(&client, profile, password, read_code).await?;
+                    println!("{}", report);
+                    Ok(true)
+                }
+            }
+        });
+        match result {
+            Ok(true) => 0,
+            Ok(false) => 1,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                1
             }
         }
     }
 
-    fn disconnect() -> () {
-        info!("Disconnecting user from server...");
-        let socket_opt = create_client_socket();
-        let socket = match socket_opt {
-            Some(s) => s,
-            None => return,
+    /// Runs `--import` or `--export` and returns the exit code.
+    fn run_profile_file(action: Action, args: &CliArgs, mut config: UserConfig) -> i32 {
+        let Some(file) = args.file.as_deref() else {
+            return 2;
         };
-        let _request = socket.send("\"Disconnect\"".to_string().as_bytes());
-        let _response = handle_response(socket);
-    }
-
-    fn save_config(config: UserConfig) -> Result<(), Error> {
-        info!("Saving config");
-        let mut file = File::create("user-config.json").unwrap();
-        let res = match config.remember_me {
-            true => file.write_all(
-                serde_json::to_string(&UserConfig {
-                    tunnel_params: remove_password(config.tunnel_params),
-                    remember_me: config.remember_me,
-                })
-                .unwrap()
-                .as_bytes(),
-            ),
-            false => file.write_all(
-                serde_json::to_string(&UserConfig {
-                    tunnel_params: TunnelParams::default(),
-                    remember_me: false,
-                })
-                .unwrap()
-                .as_bytes(),
-            ),
+        let result = match action {
+            Action::Import => profiles::import_profile(&mut config, file, args.profile.as_deref())
+                .and_then(|(name, unmapped)| {
+                    for key in unmapped {
+                        eprintln!("Skipped {}", key);
+                    }
+                    save_config(&config)?;
+                    println!("Imported profile {}", name);
+                    Ok(())
+                }),
+            _ => profiles::export_profile(&config, file, args.profile.as_deref()).map(|_| {
+                println!(
+                    "Exported profile {}",
+                    args.profile
+                        .as_deref()
+                        .unwrap_or(&config.current_profile().name)
+                );
+            }),
         };
-        res
+        match result {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                1
+            }
+        }
     }
 
-    fn parse_search_domains(search_domains: String) -> Vec<String> {
-        info!("Parsing search domains");
-        let mut domains: Vec<String> = Vec::new();
-        for domain in search_domains.split(" ") {
-            domains.push(domain.to_string());
-        }
-        domains
+    /// Asks for an MFA code on the terminal.
+    fn read_code(prompt: &str) -> Result<String, Error> {
+        eprint!("{}: ", prompt);
+        std::io::stderr().flush()?;
+        let mut code = String::new();
+        std::io::stdin().read_line(&mut code)?;
+        Ok(code)
     }
 
-    fn read_config() -> Option<UserConfig> {
-        info!("Reading config");
-        if fs::metadata(&USER_CONF_PATH).is_err() {
-            info!("Config file not found");
-            return None;
-        }
-        let file = File::open(&USER_CONF_PATH).unwrap();
-        let reader = std::io::BufReader::new(file);
-        match serde_json::from_reader(reader) {
-            Ok(params) => Some(params),
-            Err(_) => None,
-        }
+    /// Runs a blocking secret store call off the UI thread.
+    async fn with_secret_store<T: Send + 'static>(
+        store: Arc<dyn SecretStore>,
+        f: impl FnOnce(&dyn SecretStore) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        tokio::task::spawn_blocking(move || f(store.as_ref()))
+            .await
+            .map_err(Error::other)?
     }
 
-    fn remove_password(params: TunnelParams) -> TunnelParams {
-        debug!("Removing password");
-        let mut params = params.clone();
-        params.password = "".to_string();
-        params
+    fn spawn_secret_update(
+        cx: &ScopeState,
+        store: &Option<Arc<dyn SecretStore>>,
+        f: impl FnOnce(&dyn SecretStore) -> Result<(), Error> + Send + 'static,
+    ) {
+        let Some(store) = store.clone() else {
+            return;
+        };
+        cx.spawn(async move {
+            if let Err(e) = with_secret_store(store, f).await {
+                error!("Secret store error: {}", e);
+            }
+        });
     }
 }
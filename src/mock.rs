@@ -0,0 +1,405 @@
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Error;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, ToSocketAddrs, UdpSocket};
+use tokio::time::sleep;
+use url::Url;
+
+use crate::client::MAX_DATAGRAM_SIZE;
+use crate::model::{ConnectionStatus, TunnelParams, TunnelServiceRequest, TunnelServiceResponse};
+use crate::sso::{
+    query_param, read_request, write_response, SSO_REDIRECT_URI_PARAM, SSO_STATE_PARAM,
+    SSO_TOKEN_PARAM,
+};
+
+/// What the mock server does with a single incoming request.
+///
+/// Each request kind has its own script of behaviors which are consumed in
+/// order; once a script runs out every further request is accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Behavior {
+    /// Answer as a healthy service would.
+    Accept,
+    /// Answer with `TunnelServiceResponse::Error`.
+    Reject(String),
+    /// Wait before answering as `Accept` does.
+    Delay(Duration),
+    /// Do not answer at all.
+    Drop,
+    /// Answer a `Connect` with `Ok` but report the tunnel as connected only after the duration.
+    ConnectAfter(Duration),
+    /// Answer a `Connect` or `ChallengeCode` with `PasswordPrompt` instead of connecting.
+    Challenge(String),
+    /// Answer a `Connect` without an SSO token with `IdentityProvider` pointing to the URL.
+    Sso(String),
+}
+
+impl FromStr for Behavior {
+    type Err = String;
+
+    /// Parses `accept`, `reject:<message>`, `delay:<secs>`, `drop`, `connect-after:<secs>`,
+    /// `challenge:<prompt>` and `sso:<url>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        let seconds = |arg: Option<&str>| {
+            arg.and_then(|a| a.parse::<f64>().ok())
+                .filter(|a| *a >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| format!("behavior '{}' needs a number of seconds", name))
+        };
+        match name.trim() {
+            "accept" => Ok(Behavior::Accept),
+            "reject" => Ok(Behavior::Reject(
+                arg.unwrap_or("rejected by mock server").to_string(),
+            )),
+            "delay" => seconds(arg).map(Behavior::Delay),
+            "drop" => Ok(Behavior::Drop),
+            "connect-after" => seconds(arg).map(Behavior::ConnectAfter),
+            "challenge" => Ok(Behavior::Challenge(
+                arg.unwrap_or("Enter challenge code").to_string(),
+            )),
+            "sso" => arg
+                .map(|url| Behavior::Sso(url.to_string()))
+                .ok_or_else(|| "behavior 'sso' needs a login URL".to_string()),
+            other => Err(format!("unknown behavior '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for Behavior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Behavior::Accept => write!(f, "accept"),
+            Behavior::Reject(message) => write!(f, "reject:{}", message),
+            Behavior::Delay(delay) => write!(f, "delay:{}", delay.as_secs_f64()),
+            Behavior::Drop => write!(f, "drop"),
+            Behavior::ConnectAfter(delay) => write!(f, "connect-after:{}", delay.as_secs_f64()),
+            Behavior::Challenge(prompt) => write!(f, "challenge:{}", prompt),
+            Behavior::Sso(url) => write!(f, "sso:{}", url),
+        }
+    }
+}
+
+/// Parses a comma separated list of behaviors, e.g. `delay:1,reject:Wrong password`.
+pub fn parse_script(script: &str) -> Result<Vec<Behavior>, String> {
+    script
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(Behavior::from_str)
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    connect: VecDeque<Behavior>,
+    disconnect: VecDeque<Behavior>,
+    status: VecDeque<Behavior>,
+    challenge: VecDeque<Behavior>,
+    connected_since: Option<String>,
+    connect_at: Option<Instant>,
+    params: Option<TunnelParams>,
+    requests: Vec<TunnelServiceRequest>,
+}
+
+impl MockState {
+    fn next_behavior(&mut self, request: &TunnelServiceRequest) -> Behavior {
+        // The second `Connect` of an SSO login completes the first one.
+        if let TunnelServiceRequest::Connect(TunnelParams {
+            sso_token: Some(_), ..
+        }) = request
+        {
+            return Behavior::Accept;
+        }
+        let script = match request {
+            TunnelServiceRequest::Connect(_) => &mut self.connect,
+            TunnelServiceRequest::Disconnect => &mut self.disconnect,
+            TunnelServiceRequest::GetStatus => &mut self.status,
+            TunnelServiceRequest::ChallengeCode(..) => &mut self.challenge,
+        };
+        script.pop_front().unwrap_or(Behavior::Accept)
+    }
+
+    fn respond(
+        &mut self,
+        request: &TunnelServiceRequest,
+        behavior: &Behavior,
+    ) -> TunnelServiceResponse {
+        if let Behavior::Reject(message) = behavior {
+            return TunnelServiceResponse::Error(message.clone());
+        }
+        match request {
+            TunnelServiceRequest::Connect(params)
+            | TunnelServiceRequest::ChallengeCode(_, params) => {
+                self.params = Some(params.clone());
+                match behavior {
+                    Behavior::ConnectAfter(delay) => {
+                        self.connect_at = Some(Instant::now() + *delay)
+                    }
+                    Behavior::Challenge(prompt) => {
+                        return TunnelServiceResponse::PasswordPrompt(prompt.clone())
+                    }
+                    Behavior::Sso(url) => {
+                        return TunnelServiceResponse::IdentityProvider(url.clone())
+                    }
+                    _ => self.set_connected(),
+                }
+                TunnelServiceResponse::Ok
+            }
+            TunnelServiceRequest::Disconnect => {
+                self.connected_since = None;
+                self.connect_at = None;
+                TunnelServiceResponse::Ok
+            }
+            TunnelServiceRequest::GetStatus => {
+                if self.connect_at.is_some_and(|at| at <= Instant::now()) {
+                    self.set_connected();
+                }
+                TunnelServiceResponse::ConnectionStatus(self.connection_status())
+            }
+        }
+    }
+
+    fn connection_status(&self) -> ConnectionStatus {
+        match (&self.connected_since, &self.params) {
+            (Some(since), Some(params)) => ConnectionStatus {
+                connected_since: Some(since.clone()),
+                server_name: Some(params.server_name.clone()),
+                tunnel_type: Some(params.tunnel_type),
+                ip_address: Some("10.0.0.2/24".to_string()),
+                dns_servers: Some(vec!["10.0.0.53".to_string()]),
+                routes: Some(vec!["10.0.0.0/8".to_string()]),
+            },
+            _ => ConnectionStatus {
+                connected_since: self.connected_since.clone(),
+                ..ConnectionStatus::default()
+            },
+        }
+    }
+
+    fn set_connected(&mut self) {
+        self.connect_at = None;
+        if self.connected_since.is_none() {
+            self.connected_since = Some(chrono::Local::now().to_rfc3339());
+        }
+    }
+}
+
+/// Cloneable view into a running `MockServer`, used to inspect what it received.
+#[derive(Debug, Clone)]
+pub struct MockHandle {
+    local_addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// All requests received so far, including dropped ones.
+    pub fn requests(&self) -> Vec<TunnelServiceRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().connected_since.is_some()
+    }
+}
+
+/// Local stand-in for the snx-rs service in `-m command` mode.
+pub struct MockServer {
+    socket: Arc<UdpSocket>,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    pub async fn bind(address: impl ToSocketAddrs) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(address).await?;
+        Ok(MockServer {
+            socket: Arc::new(socket),
+            state: Arc::new(Mutex::new(MockState::default())),
+        })
+    }
+
+    pub fn on_connect(self, script: impl IntoIterator<Item = Behavior>) -> Self {
+        self.state.lock().unwrap().connect.extend(script);
+        self
+    }
+
+    pub fn on_disconnect(self, script: impl IntoIterator<Item = Behavior>) -> Self {
+        self.state.lock().unwrap().disconnect.extend(script);
+        self
+    }
+
+    pub fn on_status(self, script: impl IntoIterator<Item = Behavior>) -> Self {
+        self.state.lock().unwrap().status.extend(script);
+        self
+    }
+
+    pub fn on_challenge(self, script: impl IntoIterator<Item = Behavior>) -> Self {
+        self.state.lock().unwrap().challenge.extend(script);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr()
+    }
+
+    pub fn handle(&self) -> Result<MockHandle, Error> {
+        Ok(MockHandle {
+            local_addr: self.local_addr()?,
+            state: self.state.clone(),
+        })
+    }
+
+    /// Starts serving in a background task and returns a handle to it.
+    pub fn spawn(self) -> Result<MockHandle, Error> {
+        let handle = self.handle()?;
+        tokio::spawn(async move {
+            if let Err(e) = self.run().await {
+                warn!("Mock server stopped: {}", e);
+            }
+        });
+        Ok(handle)
+    }
+
+    /// Serves requests until the socket fails.
+    pub async fn run(self) -> Result<(), Error> {
+        info!("Mock server listening on {}", self.local_addr()?);
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let (size, peer) = self.socket.recv_from(&mut buf).await?;
+            let request: TunnelServiceRequest = match serde_json::from_slice(&buf[..size]) {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("Ignoring malformed request from {}: {}", peer, e);
+                    continue;
+                }
+            };
+            let behavior = {
+                let mut state = self.state.lock().unwrap();
+                state.requests.push(request.clone());
+                state.next_behavior(&request)
+            };
+            debug!(
+                "Request {:?} from {} handled as {}",
+                request, peer, behavior
+            );
+            let socket = self.socket.clone();
+            let state = self.state.clone();
+            tokio::spawn(async move {
+                match behavior {
+                    Behavior::Drop => return,
+                    Behavior::Delay(delay) => sleep(delay).await,
+                    _ => {}
+                }
+                let response = state.lock().unwrap().respond(&request, &behavior);
+                match serde_json::to_vec(&response) {
+                    Ok(payload) => {
+                        if let Err(e) = socket.send_to(&payload, peer).await {
+                            warn!("Cannot answer {}: {}", peer, e);
+                        }
+                    }
+                    Err(e) => warn!("Cannot serialize response: {}", e),
+                }
+            });
+        }
+    }
+}
+
+/// Fake identity provider for trying SSO logins without Azure.
+///
+/// `/login` shows a page with a sign in button, signing in redirects to the `redirect_uri` of the
+/// login URL with a fixed token and its `state`.
+pub struct MockIdentityProvider {
+    listener: TcpListener,
+    token: String,
+}
+
+impl MockIdentityProvider {
+    pub async fn bind(address: impl ToSocketAddrs, token: &str) -> Result<Self, Error> {
+        Ok(MockIdentityProvider {
+            listener: TcpListener::bind(address).await?,
+            token: token.to_string(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr()
+    }
+
+    /// URL of the login page, to be used in an `sso:<url>` behavior.
+    pub fn login_url(&self) -> Result<String, Error> {
+        Ok(format!("http://{}/login", self.local_addr()?))
+    }
+
+    pub fn spawn(self) -> Result<SocketAddr, Error> {
+        let address = self.local_addr()?;
+        tokio::spawn(async move {
+            if let Err(e) = self.run().await {
+                warn!("Mock identity provider stopped: {}", e);
+            }
+        });
+        Ok(address)
+    }
+
+    pub async fn run(self) -> Result<(), Error> {
+        info!("Mock identity provider listening on {}", self.local_addr()?);
+        loop {
+            let (mut stream, peer) = self.listener.accept().await?;
+            let url = match read_request(&mut stream).await {
+                Ok(url) => url,
+                Err(e) => {
+                    warn!("Ignoring bad request from {}: {}", peer, e);
+                    continue;
+                }
+            };
+            debug!("Identity provider request {}", url);
+            let redirect_uri = query_param(&url, SSO_REDIRECT_URI_PARAM);
+            let state = query_param(&url, SSO_STATE_PARAM).unwrap_or_default();
+            let result = match (url.path(), redirect_uri) {
+                ("/login", Some(_)) => {
+                    // Signing in keeps the query, which carries the redirect URI and the state.
+                    let page = "<html><body><h3>Mock identity provider</h3>\
+                        <button onclick=\"location.pathname = '/authorize'\">Sign in</button>\
+                        </body></html>";
+                    write_response(&mut stream, "200 OK", &[], page).await
+                }
+                ("/authorize", Some(redirect_uri)) => match Url::parse(&redirect_uri) {
+                    Ok(mut location) => {
+                        location
+                            .query_pairs_mut()
+                            .append_pair(SSO_TOKEN_PARAM, &self.token)
+                            .append_pair(SSO_STATE_PARAM, &state);
+                        write_response(
+                            &mut stream,
+                            "302 Found",
+                            &[("Location", location.as_str())],
+                            "",
+                        )
+                        .await
+                    }
+                    Err(_) => {
+                        write_response(&mut stream, "400 Bad Request", &[], "bad redirect_uri")
+                            .await
+                    }
+                },
+                ("/login" | "/authorize", None) => {
+                    write_response(&mut stream, "400 Bad Request", &[], "no redirect_uri").await
+                }
+                _ => write_response(&mut stream, "404 Not Found", &[], "").await,
+            };
+            if let Err(e) = result {
+                warn!("Cannot answer {}: {}", peer, e);
+            }
+        }
+    }
+}
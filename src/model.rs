@@ -1,10 +1,24 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::client::DEFAULT_SERVER_ADDRESS;
+use crate::config::{CONFIG_VERSION, DEFAULT_PROFILE_NAME};
+use crate::pinning::Fingerprint;
+use crate::protocol::Protocol;
+use crate::validation::check_domain;
+
+/// Status reported by the service. Everything but `connected_since` is only
+/// reported with the extended protocol, see `protocol::Protocol`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConnectionStatus {
     pub connected_since: Option<String>,
+    pub server_name: Option<String>,
+    pub tunnel_type: Option<TunnelType>,
+    pub ip_address: Option<String>,
+    pub dns_servers: Option<Vec<String>>,
+    pub routes: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +26,8 @@ pub enum TunnelServiceRequest {
     Connect(TunnelParams),
     Disconnect,
     GetStatus,
+    /// Answer to a `PasswordPrompt`, e.g. an OTP code, sent with the params of the pending `Connect`.
+    ChallengeCode(String, TunnelParams),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,23 +35,38 @@ pub enum TunnelServiceResponse {
     Ok,
     Error(String),
     ConnectionStatus(ConnectionStatus),
+    /// The server asks for a second factor, the text is the prompt to show to the user.
+    PasswordPrompt(String),
+    /// The server needs an SSO login at this URL, the resulting token is sent with a new `Connect`.
+    IdentityProvider(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TunnelParams {
     pub server_name: String,
     pub user_name: String,
     pub password: String,
     pub log_level: String,
     pub reauth: bool,
+    /// Without duplicates, older configs hold `[""]` for none.
+    #[serde(deserialize_with = "domain_list")]
     pub search_domains: Vec<String>,
     pub default_route: bool,
     pub no_routing: bool,
     pub no_dns: bool,
     pub no_cert_check: bool,
     pub tunnel_type: TunnelType,
-    pub ca_cert: Option<PathBuf>,
+    /// CA certificate files, older configs and services hold a single path or `null`.
+    #[serde(deserialize_with = "one_or_many")]
+    pub ca_cert: Vec<PathBuf>,
     pub login_type: LoginType,
+    /// SHA-256 fingerprints of the gateway certificates to accept, checked before `Connect` and
+    /// never sent to the service.
+    pub pinned_fingerprints: Vec<Fingerprint>,
+    /// Token from the identity provider for `LoginType::SsoAzure`, never saved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sso_token: Option<String>,
 }
 
 impl Default for TunnelParams {
@@ -46,22 +77,189 @@ impl Default for TunnelParams {
             password: String::new(),
             log_level: String::from("info"),
             reauth: true,
-            search_domains: vec!["".to_string()],
+            search_domains: Vec::new(),
             default_route: false,
             no_routing: false,
             no_dns: false,
             no_cert_check: false,
             tunnel_type: TunnelType::default(),
-            ca_cert: None,
+            ca_cert: Vec::new(),
             login_type: LoginType::default(),
+            pinned_fingerprints: Vec::new(),
+            sso_token: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UserConfig {
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        None => Vec::new(),
+        Some(OneOrMany::One(path)) => vec![path],
+        Some(OneOrMany::Many(paths)) => paths,
+    })
+}
+
+fn domain_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let domains = Option::<Vec<String>>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(dedup_domains(domains))
+}
+
+/// Whether two domain names are the same, ignoring case and a trailing dot.
+pub(crate) fn same_domain(a: &str, b: &str) -> bool {
+    let a = a.strip_suffix('.').unwrap_or(a);
+    let b = b.strip_suffix('.').unwrap_or(b);
+    a.eq_ignore_ascii_case(b)
+}
+
+/// Trims `domains` and drops empty and repeated ones, keeping the first occurrence.
+pub fn dedup_domains(domains: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for domain in domains {
+        let domain = domain.trim();
+        if !domain.is_empty() && !unique.iter().any(|d| same_domain(d, domain)) {
+            unique.push(domain.to_string());
+        }
+    }
+    unique
+}
+
+impl TunnelParams {
+    /// Appends the domains in `text`, separated by spaces or commas, that are not listed yet.
+    ///
+    /// Nothing is added when one of them is not a valid domain name.
+    pub fn add_search_domains(&mut self, text: &str) -> Result<(), String> {
+        let domains: Vec<&str> = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        for domain in &domains {
+            check_domain(domain)?;
+        }
+        let domains = self
+            .search_domains
+            .drain(..)
+            .chain(domains.into_iter().map(str::to_string));
+        self.search_domains = dedup_domains(domains);
+        Ok(())
+    }
+
+    /// Moves the search domain at `from` to `to`, DNS suffixes are tried in list order.
+    pub fn move_search_domain(&mut self, from: usize, to: usize) {
+        if from < self.search_domains.len() && to < self.search_domains.len() {
+            let domain = self.search_domains.remove(from);
+            self.search_domains.insert(to, domain);
+        }
+    }
+}
+
+/// Named set of connection settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
     pub tunnel_params: TunnelParams,
+    /// Keep the password in the system secret service.
+    pub save_password: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile::new(DEFAULT_PROFILE_NAME)
+    }
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Profile {
+            name: name.to_string(),
+            tunnel_params: TunnelParams::default(),
+            save_password: false,
+        }
+    }
+}
+
+/// On-disk configuration, see `config` for how older versions are migrated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    pub version: u32,
+    pub profiles: Vec<Profile>,
+    pub last_profile: Option<String>,
     pub remember_me: bool,
+    pub service_address: String,
+    pub notifications: NotificationSettings,
+    pub reconnect: ReconnectSettings,
+    /// Service protocol chosen by the user, `None` to detect it from the service.
+    #[serde(alias = "protocol_version")]
+    pub protocol: Option<Protocol>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            version: CONFIG_VERSION,
+            profiles: vec![Profile::new(DEFAULT_PROFILE_NAME)],
+            last_profile: Some(DEFAULT_PROFILE_NAME.to_string()),
+            remember_me: false,
+            service_address: DEFAULT_SERVER_ADDRESS.to_string(),
+            notifications: NotificationSettings::default(),
+            reconnect: ReconnectSettings::default(),
+            protocol: None,
+        }
+    }
+}
+
+/// Reconnecting after the tunnel drops, off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectSettings {
+    pub enabled: bool,
+    pub max_attempts: u32,
+    /// Delay before the first attempt, doubled for every further one.
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for ReconnectSettings {
+    fn default() -> Self {
+        ReconnectSettings {
+            enabled: false,
+            max_attempts: 5,
+            initial_delay_secs: 5,
+            max_delay_secs: 300,
+        }
+    }
+}
+
+/// Which connection changes raise a desktop notification, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub tunnel_up: bool,
+    pub tunnel_dropped: bool,
+    pub service_stopped: bool,
+}
+
+impl NotificationSettings {
+    pub fn any_enabled(&self) -> bool {
+        self.tunnel_up || self.tunnel_dropped || self.service_stopped
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            tunnel_up: true,
+            tunnel_dropped: true,
+            service_stopped: true,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
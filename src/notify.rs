@@ -0,0 +1,122 @@
+use log::debug;
+use std::fmt;
+
+use crate::model::NotificationSettings;
+use crate::state::ConnectionState;
+
+/// Connection change worth telling the user about while the window is out of sight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionNotification {
+    TunnelUp {
+        server: Option<String>,
+    },
+    /// The tunnel went down without the user disconnecting.
+    TunnelDropped,
+    ServiceStopped,
+}
+
+impl ConnectionNotification {
+    /// The notification for a status poll moving the GUI from `previous` to `next`.
+    ///
+    /// A user-initiated disconnect passes through `Disconnecting` and is not reported. When the
+    /// service stops while connected only `ServiceStopped` is reported, the tunnel state is unknown.
+    pub fn for_change(
+        previous: &ConnectionState,
+        next: &ConnectionState,
+        service_was_running: bool,
+        service_running: bool,
+        server: Option<&str>,
+    ) -> Option<Self> {
+        if service_was_running && !service_running {
+            return Some(ConnectionNotification::ServiceStopped);
+        }
+        if previous.is_drop(next) {
+            return Some(ConnectionNotification::TunnelDropped);
+        }
+        match (previous, next) {
+            (ConnectionState::Connected, ConnectionState::Connected) => None,
+            (_, ConnectionState::Connected) => Some(ConnectionNotification::TunnelUp {
+                server: server.map(str::to_string),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_enabled(&self, settings: &NotificationSettings) -> bool {
+        match self {
+            ConnectionNotification::TunnelUp { .. } => settings.tunnel_up,
+            ConnectionNotification::TunnelDropped => settings.tunnel_dropped,
+            ConnectionNotification::ServiceStopped => settings.service_stopped,
+        }
+    }
+
+    pub fn summary(&self) -> &'static str {
+        match self {
+            ConnectionNotification::TunnelUp { .. } => "VPN connected",
+            ConnectionNotification::TunnelDropped => "VPN connection lost",
+            ConnectionNotification::ServiceStopped => "snx-rs service stopped",
+        }
+    }
+
+    /// Icon from the freedesktop icon theme.
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            ConnectionNotification::TunnelUp { .. } => "network-vpn",
+            ConnectionNotification::TunnelDropped => "network-vpn-disconnected",
+            ConnectionNotification::ServiceStopped => "network-error",
+        }
+    }
+}
+
+impl fmt::Display for ConnectionNotification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionNotification::TunnelUp {
+                server: Some(server),
+            } => write!(f, "Tunnel to {} is up", server),
+            ConnectionNotification::TunnelUp { server: None } => write!(f, "Tunnel is up"),
+            ConnectionNotification::TunnelDropped => {
+                write!(f, "The tunnel went down without a disconnect request")
+            }
+            ConnectionNotification::ServiceStopped => {
+                write!(f, "The snx-rs service is not responding")
+            }
+        }
+    }
+}
+
+/// Shows `notification` through org.freedesktop.Notifications, unless `settings` opt out of it.
+///
+/// Sending happens on its own thread, failures are only logged.
+pub fn notify(notification: ConnectionNotification, settings: &NotificationSettings) {
+    if !notification.is_enabled(settings) {
+        debug!("Notification {:?} is disabled", notification);
+        return;
+    }
+    show(notification);
+}
+
+#[cfg(feature = "notifications")]
+fn show(notification: ConnectionNotification) {
+    use log::warn;
+
+    std::thread::spawn(move || {
+        let result = notify_rust::Notification::new()
+            .appname("snx-rs-gui")
+            .summary(notification.summary())
+            .body(&notification.to_string())
+            .icon(notification.icon_name())
+            .show();
+        if let Err(e) = result {
+            warn!("Cannot show notification: {}", e);
+        }
+    });
+}
+
+#[cfg(not(feature = "notifications"))]
+fn show(notification: ConnectionNotification) {
+    debug!(
+        "Built without notifications, not showing {:?}",
+        notification
+    );
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider,
+};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+use crate::certificate::{parse_der, CertificateInfo};
+use crate::model::TunnelParams;
+
+/// Port of the gateway when the server name has none.
+pub const GATEWAY_PORT: u16 = 443;
+/// How long fetching the certificate of the gateway may take.
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const FINGERPRINT_LENGTH: usize = 32;
+
+/// SHA-256 fingerprint of a DER certificate, written as colon separated hex like `openssl x509
+/// -fingerprint -sha256` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; FINGERPRINT_LENGTH]);
+
+impl Fingerprint {
+    pub fn of(der: &[u8]) -> Self {
+        Fingerprint(Sha256::digest(der).into())
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hex: Vec<String> = self.0.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{}", hex.join(":"))
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    /// Reads hex digits, optionally separated by colons or spaces, in any case.
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a SHA-256 fingerprint", text.trim()),
+            )
+        };
+        let digits: Vec<u8> = text
+            .bytes()
+            .filter(|c| *c != b':' && !c.is_ascii_whitespace())
+            .map(|c| (c as char).to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        if digits.len() != FINGERPRINT_LENGTH * 2 {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; FINGERPRINT_LENGTH];
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks_exact(2)) {
+            *byte = pair[0] << 4 | pair[1];
+        }
+        Ok(Fingerprint(bytes))
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Certificate a gateway presented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerCertificate {
+    pub server: String,
+    pub fingerprint: Fingerprint,
+    /// `None` when the certificate cannot be read, it can still be pinned.
+    pub info: Option<CertificateInfo>,
+}
+
+/// What the gateway presented compared to the pinned certificates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerCheck {
+    /// Nothing is pinned, the certificate can be trusted on first use.
+    Unpinned(ServerCertificate),
+    Pinned(ServerCertificate),
+    /// Another certificate than the pinned ones, the connection may be intercepted.
+    Mismatch(ServerCertificate),
+}
+
+impl ServerCheck {
+    pub fn new(pins: &[Fingerprint], certificate: ServerCertificate) -> Self {
+        if pins.is_empty() {
+            ServerCheck::Unpinned(certificate)
+        } else if pins.contains(&certificate.fingerprint) {
+            ServerCheck::Pinned(certificate)
+        } else {
+            ServerCheck::Mismatch(certificate)
+        }
+    }
+
+    pub fn certificate(&self) -> &ServerCertificate {
+        match self {
+            ServerCheck::Unpinned(certificate)
+            | ServerCheck::Pinned(certificate)
+            | ServerCheck::Mismatch(certificate) => certificate,
+        }
+    }
+
+    /// An `InvalidData` error on a mismatch, checked before connecting.
+    ///
+    /// snx-rs opens its own connection and knows nothing about the pins, so it still verifies the
+    /// certificate as `no_cert_check` and `ca_cert` say.
+    pub fn verify(&self) -> Result<(), Error> {
+        match self {
+            ServerCheck::Unpinned(_) | ServerCheck::Pinned(_) => Ok(()),
+            ServerCheck::Mismatch(certificate) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The certificate of {} changed, SHA-256 {} is not pinned. \
+                     Someone may be intercepting the connection",
+                    certificate.server, certificate.fingerprint
+                ),
+            )),
+        }
+    }
+}
+
+/// Host and port of a server name as validated by `check_server_name`.
+pub fn gateway_address(server: &str) -> (String, u16) {
+    let server = server.trim();
+    if let Ok(address) = server.parse::<SocketAddr>() {
+        return (address.ip().to_string(), address.port());
+    }
+    let bare = server
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(server);
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return (ip.to_string(), GATEWAY_PORT);
+    }
+    match server
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+    {
+        Some((host, port)) => (host.to_string(), port),
+        None => (server.to_string(), GATEWAY_PORT),
+    }
+}
+
+/// Accepts any certificate, which is compared to the pins afterwards. The handshake signature is
+/// still verified, so the server has to hold the key of the certificate it presents.
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Opens a TLS connection to the gateway and returns the certificate it presents.
+pub async fn fetch_server_certificate(server: &str) -> Result<ServerCertificate, Error> {
+    let (host, port) = gateway_address(server);
+    let provider = Arc::new(ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(Error::other)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+        .with_no_client_auth();
+    let name =
+        ServerName::try_from(host.clone()).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let handshake = async {
+        let stream = TcpStream::connect((host.as_str(), port)).await?;
+        TlsConnector::from(Arc::new(config))
+            .connect(name, stream)
+            .await
+    };
+    let stream = timeout(FETCH_TIMEOUT, handshake).await.map_err(|_| {
+        Error::new(
+            ErrorKind::TimedOut,
+            format!("{} did not complete a TLS handshake", server),
+        )
+    })??;
+    let der = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "The server sent no certificate"))?;
+    Ok(ServerCertificate {
+        server: server.trim().to_string(),
+        fingerprint: Fingerprint::of(der),
+        info: parse_der(der).ok(),
+    })
+}
+
+/// Fetches the certificate of the gateway in `params` and compares it to the pinned ones.
+pub async fn check_server(params: &TunnelParams) -> Result<ServerCheck, Error> {
+    let certificate = fetch_server_certificate(&params.server_name).await?;
+    Ok(ServerCheck::new(&params.pinned_fingerprints, certificate))
+}
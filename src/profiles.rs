@@ -0,0 +1,76 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::model::{Profile, UserConfig};
+use crate::secrets::SecretStore;
+use crate::snx_config::{read_snx_config, write_snx_config, UnmappedKey};
+
+/// The profile called `name`, or the last one used when not given.
+pub fn select_profile<'a>(
+    config: &'a UserConfig,
+    name: Option<&str>,
+) -> Result<&'a Profile, Error> {
+    match name {
+        Some(name) => config.profile(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Profile {} does not exist", name),
+            )
+        }),
+        None => Ok(config.current_profile()),
+    }
+}
+
+/// Adds a profile with the settings of the snx-rs config file at `path` and selects it.
+///
+/// The profile is called `name`, or after the file when not given. Returns the name and the
+/// lines that could not be imported.
+pub fn import_profile(
+    config: &mut UserConfig,
+    path: &Path,
+    name: Option<&str>,
+) -> Result<(String, Vec<UnmappedKey>), Error> {
+    let import = read_snx_config(path)?;
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+            config.unique_profile_name(stem.as_deref().unwrap_or("Imported"))
+        }
+    };
+    config.import_profile(&name, import.params)?;
+    Ok((name, import.unmapped))
+}
+
+/// Writes the profile named `name`, or the last used one, to `path` as an snx-rs config file.
+pub fn export_profile(config: &UserConfig, path: &Path, name: Option<&str>) -> Result<(), Error> {
+    let profile = select_profile(config, name)?;
+    write_snx_config(path, &profile.tunnel_params)
+}
+
+/// The password saved for `profile`, falling back to `fallback`, e.g. the one typed in the GUI.
+pub async fn profile_password(
+    profile: &Profile,
+    store: Option<Arc<dyn SecretStore>>,
+    fallback: Option<String>,
+) -> Result<String, Error> {
+    if let Some(store) = store.filter(|_| profile.save_password) {
+        let (name, user_name) = (
+            profile.name.clone(),
+            profile.tunnel_params.user_name.clone(),
+        );
+        let saved = tokio::task::spawn_blocking(move || store.get_password(&name, &user_name))
+            .await
+            .map_err(Error::other)??;
+        if let Some(password) = saved {
+            return Ok(password);
+        }
+    }
+    fallback.filter(|p| !p.is_empty()).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("No password for profile {}", profile.name),
+        )
+    })
+}
@@ -0,0 +1,158 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use crate::model::{ConnectionStatus, LoginType, TunnelParams, TunnelServiceRequest};
+
+/// `TunnelParams` fields of the `Connect` request the GUI has always sent to snx-rs.
+const BASIC_PARAMS: &[&str] = &[
+    "server_name",
+    "user_name",
+    "password",
+    "log_level",
+    "reauth",
+    "search_domains",
+    "default_route",
+    "no_routing",
+    "no_dns",
+    "no_cert_check",
+    "tunnel_type",
+    "ca_cert",
+    "login_type",
+];
+
+/// `TunnelParams` fields only sent with the extended protocol.
+const EXTENDED_PARAMS: &[&str] = &["sso_token"];
+
+/// `TunnelParams` fields the GUI keeps for itself and never sends.
+const LOCAL_PARAMS: &[&str] = &["pinned_fingerprints"];
+
+/// Messages the service understands.
+///
+/// The basic protocol is the one the snx-rs releases listed in the README are used with: `Connect`,
+/// `Disconnect` and `GetStatus`, answered with a status that only carries `connected_since`. The
+/// extended protocol adds challenge codes, SSO logins and connection details, as spoken by
+/// snx-rs-mock. Services that implement it are told apart by the details in their status, until
+/// then the basic protocol is assumed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    /// Earlier builds saved it as "0.3".
+    #[default]
+    #[serde(rename = "basic", alias = "0.3")]
+    Basic,
+    /// Earlier builds saved it as "0.4".
+    #[serde(rename = "extended", alias = "0.4")]
+    Extended,
+}
+
+/// Setting of a profile that the service cannot act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedOption {
+    /// `LoginType::SsoAzure`, the service never asks for the identity provider login.
+    SsoLogin,
+    /// `LoginType::PasswordWithMfa`, challenge codes cannot be sent to the service.
+    ChallengeCodes,
+}
+
+impl fmt::Display for UnsupportedOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnsupportedOption::SsoLogin => write!(f, "SSO Azure login"),
+            UnsupportedOption::ChallengeCodes => write!(f, "MFA challenge codes"),
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Protocol::Basic => write!(f, "basic"),
+            Protocol::Extended => write!(f, "extended"),
+        }
+    }
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 2] = [Protocol::Basic, Protocol::Extended];
+
+    /// Guesses the protocol from a status report, `None` when it does not tell.
+    ///
+    /// Only the extended protocol reports connection details, so a connected status without any
+    /// is basic. A disconnected status looks the same for both.
+    pub fn detect(status: &ConnectionStatus) -> Option<Self> {
+        let details = status.server_name.is_some()
+            || status.tunnel_type.is_some()
+            || status.ip_address.is_some()
+            || status.dns_servers.is_some()
+            || status.routes.is_some();
+        match (details, status.is_connected()) {
+            (true, _) => Some(Protocol::Extended),
+            (false, true) => Some(Protocol::Basic),
+            (false, false) => None,
+        }
+    }
+
+    /// Settings in `params` that this protocol ignores or cannot complete a login with.
+    pub fn unsupported_options(self, params: &TunnelParams) -> Vec<UnsupportedOption> {
+        match (self, params.login_type) {
+            (Protocol::Basic, LoginType::SsoAzure) => vec![UnsupportedOption::SsoLogin],
+            (Protocol::Basic, LoginType::PasswordWithMfa) => {
+                vec![UnsupportedOption::ChallengeCodes]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn sends_param(self, field: &str) -> bool {
+        BASIC_PARAMS.contains(&field)
+            || (self == Protocol::Extended && EXTENDED_PARAMS.contains(&field))
+    }
+
+    /// Encodes `request` the way this protocol expects it on the wire.
+    ///
+    /// Params the protocol does not know are left out, requests it does not know fail with
+    /// `ErrorKind::Unsupported`.
+    pub fn encode(self, request: &TunnelServiceRequest) -> Result<Vec<u8>, Error> {
+        if self == Protocol::Basic && matches!(request, TunnelServiceRequest::ChallengeCode(..)) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "challenge codes need the extended service protocol",
+            ));
+        }
+        let mut value = serde_json::to_value(request)?;
+        let params = match request {
+            TunnelServiceRequest::Connect(_) => value.pointer_mut("/Connect"),
+            TunnelServiceRequest::ChallengeCode(..) => value.pointer_mut("/ChallengeCode/1"),
+            _ => None,
+        };
+        if let Some(Value::Object(params)) = params {
+            for field in LOCAL_PARAMS {
+                params.remove(*field);
+            }
+            params.retain(|field, _| {
+                let known = self.sends_param(field);
+                if !known {
+                    warn!(
+                        "The {} service protocol does not support {}, leaving it out",
+                        self, field
+                    );
+                }
+                known
+            });
+            // Both protocols take a single CA certificate path, see `certificate::bundle_ca_certs`.
+            if let Some(ca_cert) = params.get_mut("ca_cert") {
+                let mut certs = ca_cert.as_array().cloned().unwrap_or_default();
+                if certs.len() > 1 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "several CA certificate files have to be bundled into one",
+                    ));
+                }
+                *ca_cert = certs.pop().unwrap_or(Value::Null);
+            }
+        }
+        Ok(serde_json::to_vec(&value)?)
+    }
+}
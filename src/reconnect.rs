@@ -0,0 +1,98 @@
+use chrono::{DateTime, Local};
+use std::time::Duration;
+
+use crate::model::ReconnectSettings;
+use crate::state::ConnectionState;
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl From<&ReconnectSettings> for ReconnectPolicy {
+    fn from(settings: &ReconnectSettings) -> Self {
+        ReconnectPolicy {
+            max_attempts: settings.max_attempts,
+            initial_delay: Duration::from_secs(settings.initial_delay_secs),
+            max_delay: Duration::from_secs(settings.max_delay_secs),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before `attempt`, counted from 1, or `None` once the attempts are used up.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        Some(
+            self.initial_delay
+                .saturating_mul(factor)
+                .min(self.max_delay),
+        )
+    }
+}
+
+/// Progress of reconnecting after the tunnel dropped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Reconnect {
+    #[default]
+    Idle,
+    /// Counting down to `attempt`.
+    Waiting { attempt: u32, at: DateTime<Local> },
+    /// `Connect` of `attempt` is in flight.
+    Connecting { attempt: u32 },
+}
+
+impl Reconnect {
+    /// Follows the connection from `previous` to `next`.
+    ///
+    /// A drop starts the countdown to the first attempt, a failed attempt the countdown to the
+    /// next one until `settings` allow no more. Reaching `Connected`, or the user connecting,
+    /// disconnecting or cancelling in between, ends reconnecting.
+    pub fn on_state_change(
+        &self,
+        settings: &ReconnectSettings,
+        previous: &ConnectionState,
+        next: &ConnectionState,
+        now: DateTime<Local>,
+    ) -> Reconnect {
+        let policy = ReconnectPolicy::from(settings);
+        let wait = |attempt| match policy.delay(attempt) {
+            Some(delay) => Reconnect::Waiting {
+                attempt,
+                at: now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX),
+            },
+            None => Reconnect::Idle,
+        };
+        match self {
+            Reconnect::Idle if settings.enabled && previous.is_drop(next) => wait(1),
+            Reconnect::Idle => Reconnect::Idle,
+            Reconnect::Waiting { .. } => match next {
+                ConnectionState::Connected | ConnectionState::Connecting => Reconnect::Idle,
+                _ => self.clone(),
+            },
+            Reconnect::Connecting { attempt } => match next {
+                ConnectionState::Connecting => self.clone(),
+                ConnectionState::Failed(_) => wait(attempt + 1),
+                _ => Reconnect::Idle,
+            },
+        }
+    }
+
+    /// Moves a finished countdown on to its attempt.
+    pub fn start_attempt(&self) -> Reconnect {
+        match self {
+            Reconnect::Waiting { attempt, .. } => Reconnect::Connecting { attempt: *attempt },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self != Reconnect::Idle
+    }
+}
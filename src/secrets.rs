@@ -0,0 +1,127 @@
+use log::debug;
+use std::collections::HashMap;
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+
+/// Service name under which passwords are stored in the secret service.
+pub const SECRET_SERVICE_NAME: &str = "snx-rs-gui";
+/// Set to `memory` to keep passwords in memory only, e.g. when there is no desktop session.
+pub const SECRET_STORE_ENV: &str = "SNX_RS_GUI_SECRET_STORE";
+
+/// Storage for profile passwords, keyed by profile name and user name.
+pub trait SecretStore: Send + Sync {
+    fn get_password(&self, profile: &str, user_name: &str) -> Result<Option<String>, Error>;
+    fn set_password(&self, profile: &str, user_name: &str, password: &str) -> Result<(), Error>;
+    /// Removes the password, succeeds when there is none.
+    fn delete_password(&self, profile: &str, user_name: &str) -> Result<(), Error>;
+}
+
+fn account(profile: &str, user_name: &str) -> String {
+    format!("{}/{}", profile, user_name)
+}
+
+/// Moves a stored password to a new profile name, e.g. after a rename.
+pub fn move_password(
+    store: &dyn SecretStore,
+    profile: &str,
+    new_profile: &str,
+    user_name: &str,
+) -> Result<(), Error> {
+    if let Some(password) = store.get_password(profile, user_name)? {
+        store.set_password(new_profile, user_name, &password)?;
+        store.delete_password(profile, user_name)?;
+    }
+    Ok(())
+}
+
+/// Secret store selected by `SNX_RS_GUI_SECRET_STORE`, the system secret service by default.
+///
+/// Returns `None` when built without the `secret-service` feature and no in-memory store was requested.
+pub fn default_secret_store() -> Option<Arc<dyn SecretStore>> {
+    if std::env::var(SECRET_STORE_ENV).is_ok_and(|store| store == "memory") {
+        debug!("Using in-memory secret store");
+        return Some(Arc::new(MemorySecretStore::default()));
+    }
+    system_secret_store()
+}
+
+#[cfg(feature = "secret-service")]
+fn system_secret_store() -> Option<Arc<dyn SecretStore>> {
+    Some(Arc::new(SecretServiceStore))
+}
+
+#[cfg(not(feature = "secret-service"))]
+fn system_secret_store() -> Option<Arc<dyn SecretStore>> {
+    None
+}
+
+/// Secret store keeping passwords in process memory, used as a test double for the secret service.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySecretStore {
+    secrets: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl SecretStore for MemorySecretStore {
+    fn get_password(&self, profile: &str, user_name: &str) -> Result<Option<String>, Error> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .get(&account(profile, user_name))
+            .cloned())
+    }
+
+    fn set_password(&self, profile: &str, user_name: &str, password: &str) -> Result<(), Error> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .insert(account(profile, user_name), password.to_string());
+        Ok(())
+    }
+
+    fn delete_password(&self, profile: &str, user_name: &str) -> Result<(), Error> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .remove(&account(profile, user_name));
+        Ok(())
+    }
+}
+
+/// Secret store backed by the freedesktop Secret Service over D-Bus.
+///
+/// Calls block on D-Bus, so they should be made outside of the UI thread.
+#[cfg(feature = "secret-service")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SecretServiceStore;
+
+#[cfg(feature = "secret-service")]
+impl SecretServiceStore {
+    fn entry(profile: &str, user_name: &str) -> Result<keyring::Entry, Error> {
+        keyring::Entry::new(SECRET_SERVICE_NAME, &account(profile, user_name)).map_err(Error::other)
+    }
+}
+
+#[cfg(feature = "secret-service")]
+impl SecretStore for SecretServiceStore {
+    fn get_password(&self, profile: &str, user_name: &str) -> Result<Option<String>, Error> {
+        match Self::entry(profile, user_name)?.get_password() {
+            Ok(password) => Ok(Some(password)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(Error::other(e)),
+        }
+    }
+
+    fn set_password(&self, profile: &str, user_name: &str, password: &str) -> Result<(), Error> {
+        Self::entry(profile, user_name)?
+            .set_password(password)
+            .map_err(Error::other)
+    }
+
+    fn delete_password(&self, profile: &str, user_name: &str) -> Result<(), Error> {
+        match Self::entry(profile, user_name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(Error::other(e)),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use crate::model::{dedup_domains, LoginType, TunnelParams, TunnelType};
+use crate::validation::check_domain;
+
+/// Log levels offered by the settings panel.
+const LOG_LEVELS: &[&str] = &["debug", "info", "warn", "error"];
+
+/// Why a line of an snx-rs config file did not make it into the profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnmappedReason {
+    /// snx-rs-gui has no setting for the key.
+    Unsupported,
+    /// The value cannot be read as the setting's type.
+    InvalidValue(String),
+    /// Passwords are kept in the secret service, never in profiles.
+    Password,
+    /// The line is not `key=value`.
+    Malformed,
+}
+
+/// Line of an snx-rs config file that was left out of an import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmappedKey {
+    /// Counted from 1.
+    pub line: usize,
+    pub key: String,
+    pub reason: UnmappedReason,
+}
+
+impl fmt::Display for UnmappedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}: ", self.line, self.key)?;
+        match &self.reason {
+            UnmappedReason::Unsupported => write!(f, "not supported by snx-rs-gui"),
+            UnmappedReason::InvalidValue(value) => write!(f, "invalid value {}", value),
+            UnmappedReason::Password => write!(f, "passwords are not imported"),
+            UnmappedReason::Malformed => write!(f, "expected key=value"),
+        }
+    }
+}
+
+/// Settings read from an snx-rs config file.
+#[derive(Debug, Clone)]
+pub struct SnxConfigImport {
+    pub params: TunnelParams,
+    pub unmapped: Vec<UnmappedKey>,
+}
+
+/// Name snx-rs uses for `login_type` in its config file.
+fn login_type_name(login_type: LoginType) -> &'static str {
+    match login_type {
+        LoginType::Password => "vpn_Username_Password",
+        LoginType::PasswordWithMfa => "vpn",
+        LoginType::PasswordWithMsAuth => "vpn_Microsoft_Authenticator",
+        LoginType::EmergencyAccess => "vpn_Emergency_Access",
+        LoginType::SsoAzure => "vpn_Azure_Authentication",
+    }
+}
+
+fn parse_login_type(value: &str) -> Option<LoginType> {
+    [
+        LoginType::Password,
+        LoginType::PasswordWithMfa,
+        LoginType::PasswordWithMsAuth,
+        LoginType::EmergencyAccess,
+        LoginType::SsoAzure,
+    ]
+    .into_iter()
+    .find(|login_type| login_type_name(*login_type) == value)
+}
+
+fn tunnel_type_name(tunnel_type: TunnelType) -> &'static str {
+    match tunnel_type {
+        TunnelType::Ssl => "ssl",
+        TunnelType::Ipsec => "ipsec",
+    }
+}
+
+fn parse_tunnel_type(value: &str) -> Option<TunnelType> {
+    match value.to_ascii_lowercase().as_str() {
+        "ssl" => Some(TunnelType::Ssl),
+        "ipsec" => Some(TunnelType::Ipsec),
+        _ => None,
+    }
+}
+
+/// Sets the setting for `key`, `Err` when the line cannot be mapped.
+fn apply(params: &mut TunnelParams, key: &str, value: &str) -> Result<(), UnmappedReason> {
+    let invalid = || UnmappedReason::InvalidValue(value.to_string());
+    let flag = || value.parse::<bool>().map_err(|_| invalid());
+    match key {
+        "server-name" => params.server_name = value.to_string(),
+        "user-name" => params.user_name = value.to_string(),
+        "password" => return Err(UnmappedReason::Password),
+        "log-level" => {
+            let level = value.to_ascii_lowercase();
+            if !LOG_LEVELS.contains(&level.as_str()) {
+                return Err(invalid());
+            }
+            params.log_level = level;
+        }
+        "reauth" => params.reauth = flag()?,
+        "search-domains" => {
+            let domains: Vec<&str> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|domain| !domain.is_empty())
+                .collect();
+            if domains.iter().any(|domain| check_domain(domain).is_err()) {
+                return Err(invalid());
+            }
+            params.search_domains = dedup_domains(domains.into_iter().map(str::to_string));
+        }
+        "default-route" => params.default_route = flag()?,
+        "no-routing" => params.no_routing = flag()?,
+        "no-dns" => params.no_dns = flag()?,
+        "no-cert-check" => params.no_cert_check = flag()?,
+        "tunnel-type" => params.tunnel_type = parse_tunnel_type(value).ok_or_else(invalid)?,
+        "ca-cert" => {
+            params.ca_cert = value
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .collect();
+        }
+        "login-type" => params.login_type = parse_login_type(value).ok_or_else(invalid)?,
+        _ => return Err(UnmappedReason::Unsupported),
+    }
+    Ok(())
+}
+
+/// Reads the `key=value` lines of an snx-rs config file on top of the default settings.
+///
+/// Empty lines and `#` comments are skipped, values may be wrapped in double quotes. Keys
+/// snx-rs-gui cannot map are reported in `SnxConfigImport::unmapped`, a later line for the same
+/// key wins.
+pub fn import_snx_config(text: &str) -> SnxConfigImport {
+    let mut params = TunnelParams::default();
+    let mut unmapped = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            unmapped.push(UnmappedKey {
+                line: index + 1,
+                key: line.to_string(),
+                reason: UnmappedReason::Malformed,
+            });
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        if let Err(reason) = apply(&mut params, key, value) {
+            unmapped.push(UnmappedKey {
+                line: index + 1,
+                key: key.to_string(),
+                reason,
+            });
+        }
+    }
+    SnxConfigImport { params, unmapped }
+}
+
+/// Writes `params` in the snx-rs config file format, leaving out the password.
+pub fn export_snx_config(params: &TunnelParams) -> String {
+    let mut lines = vec![
+        format!("server-name={}", params.server_name),
+        format!("user-name={}", params.user_name),
+        format!("login-type={}", login_type_name(params.login_type)),
+        format!("tunnel-type={}", tunnel_type_name(params.tunnel_type)),
+        format!("log-level={}", params.log_level),
+        format!("reauth={}", params.reauth),
+    ];
+    let search_domains: Vec<&str> = params
+        .search_domains
+        .iter()
+        .map(|domain| domain.trim())
+        .filter(|domain| !domain.is_empty())
+        .collect();
+    if !search_domains.is_empty() {
+        lines.push(format!("search-domains={}", search_domains.join(",")));
+    }
+    lines.extend([
+        format!("default-route={}", params.default_route),
+        format!("no-routing={}", params.no_routing),
+        format!("no-dns={}", params.no_dns),
+        format!("no-cert-check={}", params.no_cert_check),
+    ]);
+    if !params.ca_cert.is_empty() {
+        let paths: Vec<String> = params
+            .ca_cert
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        lines.push(format!("ca-cert={}", paths.join(",")));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn read_snx_config(path: &Path) -> Result<SnxConfigImport, Error> {
+    Ok(import_snx_config(&fs::read_to_string(path)?))
+}
+
+pub fn write_snx_config(path: &Path, params: &TunnelParams) -> Result<(), Error> {
+    fs::write(path, export_snx_config(params))
+}
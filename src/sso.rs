@@ -0,0 +1,224 @@
+use log::{debug, info, warn};
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+use tokio_rustls::rustls::crypto::ring;
+use url::Url;
+
+/// Loopback address the identity provider redirects to after an SSO login, on a free port.
+pub const SSO_REDIRECT_ADDRESS: &str = "127.0.0.1:0";
+pub const SSO_REDIRECT_PATH: &str = "/sso";
+/// Query parameter of the redirect carrying the token.
+pub const SSO_TOKEN_PARAM: &str = "token";
+/// Query parameter telling the identity provider where to redirect to.
+pub const SSO_REDIRECT_URI_PARAM: &str = "redirect_uri";
+/// Query parameter the identity provider passes back unchanged, so only its redirect is accepted.
+pub const SSO_STATE_PARAM: &str = "state";
+/// Longest request line and headers the listener reads.
+const MAX_REQUEST_HEAD: u64 = 16 * 1024;
+/// How long a browser may take to send its request, stalled connections are dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const STATE_LENGTH: usize = 16;
+
+const LOGIN_DONE_PAGE: &str =
+    "<html><body><p>Login complete, you can close this window.</p></body></html>";
+const LOGIN_FAILED_PAGE: &str =
+    "<html><body><p>Login failed, you can close this window.</p></body></html>";
+
+/// Waits for the identity provider to redirect the login window back to the app.
+///
+/// The redirect is a plain `GET <SSO_REDIRECT_PATH>?token=...&state=...` on a loopback port,
+/// so it works the same from the embedded webview and from an external browser. Redirects
+/// without the `state` of this listener, e.g. from other local processes, are ignored.
+pub struct RedirectListener {
+    listener: TcpListener,
+    state: String,
+}
+
+impl RedirectListener {
+    pub async fn bind(address: impl ToSocketAddrs) -> Result<Self, Error> {
+        Ok(RedirectListener {
+            listener: TcpListener::bind(address).await?,
+            state: random_state()?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr()
+    }
+
+    /// URL the identity provider redirects to, passed along by `login_url`.
+    pub fn redirect_url(&self) -> Result<String, Error> {
+        Ok(format!(
+            "http://{}{}",
+            self.local_addr()?,
+            SSO_REDIRECT_PATH
+        ))
+    }
+
+    /// The login page at `url` with the redirect URL and the state of this listener.
+    pub fn login_url(&self, url: &str) -> Result<String, Error> {
+        let mut url = Url::parse(url).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        url.query_pairs_mut()
+            .append_pair(SSO_REDIRECT_URI_PARAM, &self.redirect_url()?)
+            .append_pair(SSO_STATE_PARAM, &self.state);
+        Ok(url.into())
+    }
+
+    /// Serves requests until the redirect arrives and returns its token.
+    ///
+    /// Requests for other paths, e.g. a favicon, are answered with 404 and ignored. Connections
+    /// are served side by side, a client that does not send its request holds up nothing.
+    pub async fn accept_token(&self) -> Result<String, Error> {
+        let mut requests = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (mut stream, peer) = accepted?;
+                    let state = self.state.clone();
+                    requests.spawn(async move {
+                        match timeout(REQUEST_TIMEOUT, serve_redirect(&mut stream, &state)).await {
+                            Ok(Ok(result)) => result,
+                            Ok(Err(e)) => {
+                                warn!("Ignoring bad request from {}: {}", peer, e);
+                                None
+                            }
+                            Err(_) => {
+                                warn!("Ignoring {}, it sent no request in time", peer);
+                                None
+                            }
+                        }
+                    });
+                }
+                Some(served) = requests.join_next() => {
+                    if let Ok(Some(result)) = served {
+                        info!("SSO redirect received");
+                        return result;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Hex encoded random value for `SSO_STATE_PARAM`.
+fn random_state() -> Result<String, Error> {
+    let mut bytes = [0u8; STATE_LENGTH];
+    ring::default_provider()
+        .secure_random
+        .fill(&mut bytes)
+        .map_err(|_| Error::other("no random numbers for the SSO state"))?;
+    let mut state = String::with_capacity(STATE_LENGTH * 2);
+    for byte in bytes {
+        let _ = write!(state, "{:02x}", byte);
+    }
+    Ok(state)
+}
+
+/// Answers a single request, returns the login result when it is the redirect for `state`.
+async fn serve_redirect(
+    stream: &mut TcpStream,
+    state: &str,
+) -> Result<Option<Result<String, Error>>, Error> {
+    let url = read_request(stream).await?;
+    if url.path() != SSO_REDIRECT_PATH {
+        debug!("Ignoring request for {}", url.path());
+        write_response(stream, "404 Not Found", &[], "").await?;
+        return Ok(None);
+    }
+    if query_param(&url, SSO_STATE_PARAM).as_deref() != Some(state) {
+        warn!("Ignoring SSO redirect with an unknown state");
+        write_response(stream, "400 Bad Request", &[], "").await?;
+        return Ok(None);
+    }
+    let result = token_from_redirect(&url);
+    let page = match result {
+        Ok(_) => LOGIN_DONE_PAGE,
+        Err(_) => LOGIN_FAILED_PAGE,
+    };
+    if let Err(e) = write_response(stream, "200 OK", &[], page).await {
+        warn!("Cannot answer the SSO redirect: {}", e);
+    }
+    Ok(Some(result))
+}
+
+pub(crate) fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// The token of a redirect, or the error reported by the identity provider.
+pub fn token_from_redirect(url: &Url) -> Result<String, Error> {
+    if let Some(error) = query_param(url, "error") {
+        let description = query_param(url, "error_description").unwrap_or(error);
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("SSO login failed: {}", description),
+        ));
+    }
+    query_param(url, SSO_TOKEN_PARAM)
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "SSO redirect carries no token"))
+}
+
+/// Reads an HTTP request head of at most `MAX_REQUEST_HEAD` bytes and returns its target as an
+/// absolute URL.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Result<Url, Error> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let too_long = || Error::new(ErrorKind::InvalidData, "request head too long");
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    if !request_line.ends_with('\n') {
+        return Err(too_long());
+    }
+    // Skip the headers, nothing in them is needed.
+    let mut header = String::new();
+    loop {
+        header.clear();
+        reader.read_line(&mut header).await?;
+        if !header.ends_with('\n') {
+            return Err(too_long());
+        }
+        if header.trim_end().is_empty() {
+            break;
+        }
+    }
+    let target = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", target, _] => target,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported request {}", request_line.trim()),
+            ))
+        }
+    };
+    Url::parse("http://localhost")
+        .and_then(|base| base.join(target))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+pub(crate) async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Result<(), Error> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
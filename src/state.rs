@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// Connection lifecycle as seen by the GUI, driving button enablement and status text.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Idle,
+    Connecting,
+    Connected,
+    Disconnecting,
+    Failed(String),
+}
+
+/// Something that happened to the connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    ConnectRequested,
+    ConnectSucceeded,
+    ConnectFailed(String),
+    /// Waiting for the tunnel to come up took too long.
+    ConnectTimedOut,
+    /// The user gave up on the attempt, e.g. by cancelling an MFA challenge.
+    ConnectCancelled,
+    DisconnectRequested,
+    DisconnectSucceeded,
+    DisconnectFailed(String),
+    /// A status poll reported the tunnel up (`true`) or down (`false`).
+    StatusPolled(bool),
+    ServiceDown,
+}
+
+impl ConnectionState {
+    /// The state after `event`, events that do not apply to the current state are ignored.
+    pub fn next(&self, event: &ConnectionEvent) -> ConnectionState {
+        use ConnectionEvent as E;
+        use ConnectionState as S;
+        match (self, event) {
+            (S::Idle | S::Failed(_), E::ConnectRequested) => S::Connecting,
+            (S::Connected | S::Connecting, E::DisconnectRequested) => S::Disconnecting,
+
+            // The service accepted the command, the tunnel may still be coming up.
+            (S::Connecting, E::ConnectSucceeded) => S::Connecting,
+            (S::Connecting, E::ConnectFailed(message)) => S::Failed(message.clone()),
+            (S::Connecting, E::ConnectTimedOut) => {
+                S::Failed("Timed out waiting for the tunnel to come up".to_string())
+            }
+            (S::Connecting, E::ConnectCancelled) => S::Idle,
+            (S::Connecting, E::StatusPolled(true)) => S::Connected,
+            (S::Connecting, E::StatusPolled(false) | E::ServiceDown) => S::Connecting,
+
+            (S::Disconnecting, E::DisconnectSucceeded) => S::Idle,
+            (S::Disconnecting, E::DisconnectFailed(_)) => S::Connected,
+            (S::Disconnecting, E::StatusPolled(false)) => S::Idle,
+            (S::Disconnecting, E::StatusPolled(true) | E::ServiceDown) => S::Disconnecting,
+
+            (S::Idle | S::Failed(_), E::StatusPolled(true)) => S::Connected,
+            (S::Connected, E::StatusPolled(false) | E::ServiceDown) => S::Idle,
+
+            (state, _) => state.clone(),
+        }
+    }
+
+    /// Moving to `next` is the tunnel going down without a disconnect request.
+    ///
+    /// Disconnects asked for in the GUI pass through `Disconnecting` instead.
+    pub fn is_drop(&self, next: &ConnectionState) -> bool {
+        *self == ConnectionState::Connected && *next == ConnectionState::Idle
+    }
+
+    pub fn is_connected(&self) -> bool {
+        *self == ConnectionState::Connected
+    }
+
+    /// A command is in flight or waiting for the tunnel to change.
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::Connecting | ConnectionState::Disconnecting
+        )
+    }
+
+    pub fn can_connect(&self) -> bool {
+        matches!(self, ConnectionState::Idle | ConnectionState::Failed(_))
+    }
+
+    /// Disconnecting while connecting cancels the attempt.
+    pub fn can_disconnect(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::Connected | ConnectionState::Connecting
+        )
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionState::Idle => write!(f, "disconnected"),
+            ConnectionState::Connecting => write!(f, "connecting…"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Disconnecting => write!(f, "disconnecting…"),
+            ConnectionState::Failed(_) => write!(f, "failed"),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Duration, Local};
+use log::{error, info};
+
+use crate::model::{ConnectionStatus, TunnelServiceResponse};
+
+impl ConnectionStatus {
+    pub fn is_connected(&self) -> bool {
+        self.connected_since.is_some()
+    }
+
+    /// Parses `connected_since`, which snx-rs reports as an RFC 3339 timestamp.
+    pub fn connected_since_time(&self) -> Option<DateTime<Local>> {
+        let since = self.connected_since.as_deref()?;
+        DateTime::parse_from_rfc3339(since)
+            .or_else(|_| DateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S%.f %:z"))
+            .ok()
+            .map(|time| time.with_timezone(&Local))
+    }
+
+    /// Time elapsed since the tunnel was established.
+    pub fn session_duration(&self, now: DateTime<Local>) -> Option<Duration> {
+        self.connected_since_time()
+            .map(|since| (now - since).max(Duration::zero()))
+    }
+}
+
+/// Formats a duration as `1d 02:03:04`, leaving out the days when there are none.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, hours, minutes, seconds) = (
+        seconds / 86_400,
+        seconds / 3_600 % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+    );
+    match days {
+        0 => format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+        _ => format!("{}d {:02}:{:02}:{:02}", days, hours, minutes, seconds),
+    }
+}
+
+/// Details of the established tunnel from a `GetStatus` response, `None` when it is down.
+pub fn connection_details(response: TunnelServiceResponse) -> Option<ConnectionStatus> {
+    match response {
+        TunnelServiceResponse::ConnectionStatus(status) => {
+            info!("Connection status: {:?}", status);
+            status.is_connected().then_some(status)
+        }
+        TunnelServiceResponse::Ok => {
+            info!("Connection status: Ok");
+            Some(ConnectionStatus::default())
+        }
+        TunnelServiceResponse::Error(error) => {
+            error!("Connection status: Error {:?}", error);
+            None
+        }
+        TunnelServiceResponse::PasswordPrompt(_) | TunnelServiceResponse::IdentityProvider(_) => {
+            info!("Connection status: waiting for login");
+            None
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use log::debug;
+use std::future::Future;
+use std::io::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tokio::time::{sleep_until, Instant};
+
+use crate::client::TunnelClient;
+use crate::model::TunnelServiceResponse;
+use crate::state::ConnectionState;
+
+/// Where status updates come from.
+///
+/// Polling sources only implement `fetch`. A source for a service that pushes changes also
+/// implements `changed`, the watch then fetches as soon as it resolves instead of waiting.
+pub trait StatusSource {
+    fn fetch(&mut self) -> impl Future<Output = Result<TunnelServiceResponse, Error>>;
+
+    /// Resolves when the service reports a change, never for sources without push support.
+    fn changed(&mut self) -> impl Future<Output = ()> {
+        std::future::pending()
+    }
+}
+
+/// Asks the service with `GetStatus`.
+///
+/// The address is looked up for every request, so changing it in the settings applies right away.
+pub struct PollingSource<F> {
+    address: F,
+}
+
+impl<F: FnMut() -> Result<SocketAddr, Error>> PollingSource<F> {
+    pub fn new(address: F) -> Self {
+        PollingSource { address }
+    }
+}
+
+impl<F: FnMut() -> Result<SocketAddr, Error>> StatusSource for PollingSource<F> {
+    fn fetch(&mut self) -> impl Future<Output = Result<TunnelServiceResponse, Error>> {
+        let address = (self.address)();
+        async move { TunnelClient::new(address?).get_status().await }
+    }
+}
+
+/// What the GUI currently shows, deciding how often the status is needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchHints {
+    pub state: ConnectionState,
+    pub service_running: bool,
+    /// The window is shown and not minimized.
+    pub visible: bool,
+    /// Something needs the status while the window is hidden, e.g. the tray icon or notifications.
+    pub background: bool,
+}
+
+impl Default for WatchHints {
+    fn default() -> Self {
+        WatchHints {
+            state: ConnectionState::default(),
+            service_running: false,
+            visible: true,
+            background: false,
+        }
+    }
+}
+
+/// Time between status requests for each kind of `WatchHints`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// While connecting or disconnecting.
+    pub busy: Duration,
+    pub connected: Duration,
+    /// Disconnected, or the service is not running.
+    pub idle: Duration,
+    /// Hidden window with `WatchHints::background`.
+    pub background: Duration,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        PollPolicy {
+            busy: Duration::from_secs(1),
+            connected: Duration::from_secs(5),
+            idle: Duration::from_secs(10),
+            background: Duration::from_secs(30),
+        }
+    }
+}
+
+impl PollPolicy {
+    /// Time until the next request, `None` while polling is paused.
+    pub fn interval(&self, hints: &WatchHints) -> Option<Duration> {
+        if hints.state.is_busy() {
+            // A command started from the tray must finish even while hidden.
+            return Some(self.busy);
+        }
+        match (hints.visible, hints.background) {
+            (false, false) => None,
+            (false, true) => Some(self.background),
+            _ if hints.state.is_connected() && hints.service_running => Some(self.connected),
+            _ => Some(self.idle),
+        }
+    }
+}
+
+/// Steers a running `watch_status`, cheap to clone.
+#[derive(Debug, Clone)]
+pub struct StatusSubscription {
+    hints: Arc<watch::Sender<WatchHints>>,
+    refresh: Arc<Notify>,
+}
+
+impl Default for StatusSubscription {
+    fn default() -> Self {
+        let (hints, _) = watch::channel(WatchHints::default());
+        StatusSubscription {
+            hints: Arc::new(hints),
+            refresh: Arc::default(),
+        }
+    }
+}
+
+impl StatusSubscription {
+    /// Fetches the status without waiting for the next interval, e.g. after a command.
+    pub fn refresh(&self) {
+        self.refresh.notify_one();
+    }
+
+    pub fn set_hints(&self, hints: WatchHints) {
+        self.hints.send_if_modified(|current| {
+            let modified = *current != hints;
+            *current = hints;
+            modified
+        });
+    }
+
+    pub fn hints(&self) -> WatchHints {
+        self.hints.borrow().clone()
+    }
+}
+
+/// Fetches the status from `source` whenever `subscription` or `policy` ask for it and hands
+/// every result to `on_update`. Runs until dropped.
+pub async fn watch_status<S: StatusSource>(
+    mut source: S,
+    policy: PollPolicy,
+    subscription: &StatusSubscription,
+    mut on_update: impl FnMut(Result<TunnelServiceResponse, Error>),
+) {
+    let mut hints = subscription.hints.subscribe();
+    let mut fetch = true;
+    let mut last_fetch = Instant::now();
+    loop {
+        if fetch {
+            on_update(source.fetch().await);
+            last_fetch = Instant::now();
+        }
+        let interval = policy.interval(&hints.borrow_and_update());
+        let next_fetch = async {
+            match interval {
+                Some(interval) => sleep_until(last_fetch + interval).await,
+                None => {
+                    debug!("Status updates paused");
+                    std::future::pending().await
+                }
+            }
+        };
+        fetch = tokio::select! {
+            _ = next_fetch => true,
+            _ = subscription.refresh.notified() => true,
+            _ = source.changed() => true,
+            // Only the interval changed, counted from the last fetch.
+            _ = hints.changed() => false,
+        };
+    }
+}
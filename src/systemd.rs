@@ -0,0 +1,426 @@
+use std::fmt;
+use std::io::Error;
+use tokio::process::Command;
+
+/// Unit installed by snx-rs.
+pub const SNX_RS_UNIT: &str = "snx-rs.service";
+/// Journal lines shown in the service panel.
+pub const JOURNAL_LINES: usize = 20;
+
+/// Action on the unit, authorized by polkit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl fmt::Display for UnitAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnitAction::Start => write!(f, "start"),
+            UnitAction::Stop => write!(f, "stop"),
+            UnitAction::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+/// State of a unit as reported by systemd.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitState {
+    /// `loaded`, or `not-found` when the unit is not installed.
+    pub load_state: String,
+    /// `active`, `inactive`, `failed`, `activating`, ...
+    pub active_state: String,
+    /// `running`, `dead`, ...
+    pub sub_state: String,
+    /// Microseconds since the epoch the unit last became active, 0 if never.
+    pub active_enter_timestamp: u64,
+}
+
+impl UnitState {
+    pub fn is_installed(&self) -> bool {
+        self.load_state != "not-found"
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active"
+    }
+
+    pub fn active_since(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        let micros = i64::try_from(self.active_enter_timestamp).ok()?;
+        match micros {
+            0 => None,
+            micros => chrono::DateTime::from_timestamp_micros(micros).map(|t| t.into()),
+        }
+    }
+}
+
+impl fmt::Display for UnitState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.is_installed() {
+            true => write!(f, "{} ({})", self.active_state, self.sub_state),
+            false => write!(f, "not installed"),
+        }
+    }
+}
+
+/// The last `lines` journal entries of the unit, oldest first.
+///
+/// Reading the system journal may need the user to be in the `systemd-journal` group.
+pub async fn journal(unit: &str, lines: usize) -> Result<Vec<String>, Error> {
+    let output = Command::new("journalctl")
+        .args(["--unit", unit, "--lines", &lines.to_string()])
+        .args(["--no-pager", "--quiet", "--output", "short-iso"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "journalctl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(all(feature = "systemd", feature = "mock"))]
+pub use self::dbus::FakeSystemd;
+#[cfg(feature = "systemd")]
+pub use self::dbus::SystemdService;
+
+/// Stand-in when built without the `systemd` feature, the unit cannot be managed.
+#[cfg(not(feature = "systemd"))]
+#[derive(Debug, Clone)]
+pub struct SystemdService;
+
+#[cfg(not(feature = "systemd"))]
+impl SystemdService {
+    pub async fn system(_unit: &str) -> Result<Self, Error> {
+        Err(Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Built without systemd support",
+        ))
+    }
+
+    pub fn unit(&self) -> &str {
+        SNX_RS_UNIT
+    }
+
+    pub async fn state(&self) -> Result<UnitState, Error> {
+        Self::system(SNX_RS_UNIT)
+            .await
+            .map(|_| UnitState::default())
+    }
+
+    pub async fn run(&self, _action: UnitAction) -> Result<(), Error> {
+        Self::system(SNX_RS_UNIT).await.map(|_| ())
+    }
+}
+
+/// Unit control over the systemd D-Bus API.
+#[cfg(feature = "systemd")]
+mod dbus {
+    use log::{debug, info};
+    use std::io::{Error, ErrorKind};
+    use zbus::fdo;
+    use zbus::proxy::{CacheProperties, MethodFlags};
+    use zbus::zvariant::OwnedObjectPath;
+    use zbus::{Connection, Proxy};
+
+    use super::{UnitAction, UnitState};
+
+    #[cfg(feature = "mock")]
+    pub use self::fake::FakeSystemd;
+
+    const SYSTEMD_DESTINATION: &str = "org.freedesktop.systemd1";
+    const SYSTEMD_PATH: &str = "/org/freedesktop/systemd1";
+    const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+    const UNIT_INTERFACE: &str = "org.freedesktop.systemd1.Unit";
+
+    /// Controls a systemd unit over D-Bus.
+    ///
+    /// Starting, stopping and restarting allow interactive authorization, so systemd asks polkit,
+    /// which prompts for the password through the desktop's authentication agent.
+    #[derive(Debug, Clone)]
+    pub struct SystemdService {
+        connection: Connection,
+        unit: String,
+    }
+
+    impl SystemdService {
+        pub fn new(connection: Connection, unit: &str) -> Self {
+            SystemdService {
+                connection,
+                unit: unit.to_string(),
+            }
+        }
+
+        /// Controls `unit` through the system bus.
+        pub async fn system(unit: &str) -> Result<Self, Error> {
+            let connection = Connection::system().await.map_err(dbus_error)?;
+            Ok(Self::new(connection, unit))
+        }
+
+        pub fn unit(&self) -> &str {
+            &self.unit
+        }
+
+        async fn proxy<'a>(
+            &'a self,
+            path: &'a str,
+            interface: &'a str,
+        ) -> Result<Proxy<'a>, Error> {
+            zbus::proxy::Builder::new(&self.connection)
+                .destination(SYSTEMD_DESTINATION)
+                .and_then(|b| b.path(path))
+                .and_then(|b| b.interface(interface))
+                .map_err(dbus_error)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .map_err(dbus_error)
+        }
+
+        pub async fn state(&self) -> Result<UnitState, Error> {
+            let manager = self.proxy(SYSTEMD_PATH, MANAGER_INTERFACE).await?;
+            // Unlike `GetUnit`, `LoadUnit` also answers for units that are not installed.
+            let path: OwnedObjectPath = manager
+                .call("LoadUnit", &(self.unit.as_str(),))
+                .await
+                .map_err(dbus_error)?;
+            let unit = self.proxy(path.as_str(), UNIT_INTERFACE).await?;
+            let property = |name: &'static str| {
+                let unit = &unit;
+                async move { unit.get_property::<String>(name).await.map_err(dbus_error) }
+            };
+            Ok(UnitState {
+                load_state: property("LoadState").await?,
+                active_state: property("ActiveState").await?,
+                sub_state: property("SubState").await?,
+                active_enter_timestamp: unit
+                    .get_property("ActiveEnterTimestamp")
+                    .await
+                    .map_err(dbus_error)?,
+            })
+        }
+
+        /// Queues `action` and returns once systemd accepted the job, not when it finished.
+        pub async fn run(&self, action: UnitAction) -> Result<(), Error> {
+            info!("Asking systemd to {} {}", action, self.unit);
+            let manager = self.proxy(SYSTEMD_PATH, MANAGER_INTERFACE).await?;
+            let job: Option<OwnedObjectPath> = manager
+                .call_with_flags(
+                    method(action),
+                    MethodFlags::AllowInteractiveAuth.into(),
+                    &(self.unit.as_str(), "replace"),
+                )
+                .await
+                .map_err(dbus_error)?;
+            debug!("Queued job {:?}", job);
+            Ok(())
+        }
+    }
+
+    fn method(action: UnitAction) -> &'static str {
+        match action {
+            UnitAction::Start => "StartUnit",
+            UnitAction::Stop => "StopUnit",
+            UnitAction::Restart => "RestartUnit",
+        }
+    }
+
+    /// Maps D-Bus errors that need the user's attention to their `ErrorKind`.
+    fn dbus_error(e: zbus::Error) -> Error {
+        let kind = match &e {
+            zbus::Error::MethodError(name, _, _) => match name.as_str() {
+                "org.freedesktop.DBus.Error.AccessDenied"
+                | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired" => {
+                    ErrorKind::PermissionDenied
+                }
+                "org.freedesktop.systemd1.NoSuchUnit" => ErrorKind::NotFound,
+                _ => ErrorKind::Other,
+            },
+            zbus::Error::FDO(e) => match e.as_ref() {
+                fdo::Error::AccessDenied(_) | fdo::Error::InteractiveAuthorizationRequired(_) => {
+                    ErrorKind::PermissionDenied
+                }
+                _ => ErrorKind::Other,
+            },
+            _ => ErrorKind::Other,
+        };
+        let message = match &e {
+            zbus::Error::MethodError(_, Some(message), _) => message.clone(),
+            e => e.to_string(),
+        };
+        Error::new(kind, message)
+    }
+
+    /// In-process systemd for tests and demos.
+    #[cfg(feature = "mock")]
+    mod fake {
+        use std::io::Error;
+        use std::sync::{Arc, Mutex};
+        use zbus::fdo;
+        use zbus::zvariant::OwnedObjectPath;
+        use zbus::{interface, Connection};
+
+        use super::{dbus_error, SystemdService, SYSTEMD_PATH};
+        use crate::systemd::UnitAction;
+
+        /// In-process stand-in for the systemd manager, served on a peer-to-peer D-Bus connection.
+        ///
+        /// It knows a single unit whose jobs complete at once. Requests are refused with `AccessDenied`
+        /// while `set_authorized(false)`, as systemd does when polkit says no.
+        #[derive(Debug, Clone)]
+        pub struct FakeSystemd {
+            unit: String,
+            state: Arc<Mutex<FakeUnit>>,
+            /// Kept open as long as the fake lives.
+            servers: Arc<Mutex<Vec<Connection>>>,
+        }
+
+        #[derive(Debug)]
+        struct FakeUnit {
+            installed: bool,
+            active: bool,
+            authorized: bool,
+            active_enter_timestamp: u64,
+            jobs: u32,
+        }
+
+        const FAKE_UNIT_PATH: &str = "/org/freedesktop/systemd1/unit/fake";
+
+        impl FakeSystemd {
+            pub fn new(unit: &str, installed: bool) -> Self {
+                FakeSystemd {
+                    unit: unit.to_string(),
+                    state: Arc::new(Mutex::new(FakeUnit {
+                        installed,
+                        active: false,
+                        authorized: true,
+                        active_enter_timestamp: 0,
+                        jobs: 0,
+                    })),
+                    servers: Arc::default(),
+                }
+            }
+
+            pub fn set_active(&self, active: bool) {
+                let mut state = self.state.lock().unwrap();
+                state.active = active;
+                if active {
+                    state.active_enter_timestamp = now_micros();
+                }
+            }
+
+            pub fn is_active(&self) -> bool {
+                self.state.lock().unwrap().active
+            }
+
+            pub fn set_authorized(&self, authorized: bool) {
+                self.state.lock().unwrap().authorized = authorized;
+            }
+
+            /// Serves the fake and returns a `SystemdService` talking to it.
+            pub async fn connect(&self) -> Result<SystemdService, Error> {
+                let (server, client) = tokio::net::UnixStream::pair()?;
+                let guid = zbus::Guid::generate();
+                let server = zbus::connection::Builder::unix_stream(server)
+                    .server(guid)
+                    .map_err(dbus_error)?
+                    .p2p()
+                    .serve_at(SYSTEMD_PATH, FakeManager(self.clone()))
+                    .and_then(|b| b.serve_at(FAKE_UNIT_PATH, FakeUnitObject(self.clone())))
+                    .map_err(dbus_error)?
+                    .build();
+                let client = zbus::connection::Builder::unix_stream(client).p2p().build();
+                let (server, client) = tokio::try_join!(server, client).map_err(dbus_error)?;
+                self.servers.lock().unwrap().push(server);
+                Ok(SystemdService::new(client, &self.unit))
+            }
+
+            fn job(&self, name: &str, action: UnitAction) -> fdo::Result<OwnedObjectPath> {
+                if name != self.unit {
+                    return Err(fdo::Error::Failed(format!("Unit {} not found.", name)));
+                }
+                let mut state = self.state.lock().unwrap();
+                if !state.authorized {
+                    return Err(fdo::Error::AccessDenied("Access denied".to_string()));
+                }
+                if !state.installed {
+                    return Err(fdo::Error::Failed(format!("Unit {} not found.", name)));
+                }
+                state.active = action != UnitAction::Stop;
+                if state.active {
+                    state.active_enter_timestamp = now_micros();
+                }
+                state.jobs += 1;
+                OwnedObjectPath::try_from(format!("/org/freedesktop/systemd1/job/{}", state.jobs))
+                    .map_err(|e| fdo::Error::Failed(e.to_string()))
+            }
+        }
+
+        fn now_micros() -> u64 {
+            chrono::Local::now().timestamp_micros().max(0) as u64
+        }
+
+        struct FakeManager(FakeSystemd);
+
+        #[interface(name = "org.freedesktop.systemd1.Manager")]
+        impl FakeManager {
+            fn load_unit(&self, _name: &str) -> fdo::Result<OwnedObjectPath> {
+                OwnedObjectPath::try_from(FAKE_UNIT_PATH)
+                    .map_err(|e| fdo::Error::Failed(e.to_string()))
+            }
+
+            fn start_unit(&self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+                self.0.job(name, UnitAction::Start)
+            }
+
+            fn stop_unit(&self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+                self.0.job(name, UnitAction::Stop)
+            }
+
+            fn restart_unit(&self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+                self.0.job(name, UnitAction::Restart)
+            }
+        }
+
+        struct FakeUnitObject(FakeSystemd);
+
+        #[interface(name = "org.freedesktop.systemd1.Unit")]
+        impl FakeUnitObject {
+            #[zbus(property)]
+            fn load_state(&self) -> String {
+                match self.0.state.lock().unwrap().installed {
+                    true => "loaded".to_string(),
+                    false => "not-found".to_string(),
+                }
+            }
+
+            #[zbus(property)]
+            fn active_state(&self) -> String {
+                match self.0.state.lock().unwrap().active {
+                    true => "active".to_string(),
+                    false => "inactive".to_string(),
+                }
+            }
+
+            #[zbus(property)]
+            fn sub_state(&self) -> String {
+                match self.0.state.lock().unwrap().active {
+                    true => "running".to_string(),
+                    false => "dead".to_string(),
+                }
+            }
+
+            #[zbus(property)]
+            fn active_enter_timestamp(&self) -> u64 {
+                self.0.state.lock().unwrap().active_enter_timestamp
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+
+use crate::state::ConnectionState;
+
+/// Connection state as shown by the tray icon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayStatus {
+    Connected,
+    /// Connecting or disconnecting.
+    Busy,
+    Disconnected,
+    ServiceDown,
+}
+
+impl TrayStatus {
+    pub fn new(state: &ConnectionState, service_running: bool) -> Self {
+        match state {
+            ConnectionState::Connected => TrayStatus::Connected,
+            state if state.is_busy() => TrayStatus::Busy,
+            _ if !service_running => TrayStatus::ServiceDown,
+            _ => TrayStatus::Disconnected,
+        }
+    }
+
+    /// Icon from the freedesktop icon theme.
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            TrayStatus::Connected => "network-vpn",
+            TrayStatus::Busy => "network-vpn-acquiring",
+            TrayStatus::Disconnected => "network-vpn-disconnected",
+            TrayStatus::ServiceDown => "network-error",
+        }
+    }
+}
+
+impl fmt::Display for TrayStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrayStatus::Connected => write!(f, "Connected"),
+            TrayStatus::Busy => write!(f, "Working…"),
+            TrayStatus::Disconnected => write!(f, "Disconnected"),
+            TrayStatus::ServiceDown => write!(f, "snx-rs service is not running"),
+        }
+    }
+}
+
+/// Menu entry picked in the tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    /// Connect with the last used profile.
+    Connect,
+    Disconnect,
+    ShowWindow,
+    Quit,
+}
+
+/// Everything the tray icon and its menu show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayModel {
+    pub status: TrayStatus,
+    pub can_connect: bool,
+    pub can_disconnect: bool,
+    pub profile: String,
+}
+
+impl TrayModel {
+    pub fn new(state: &ConnectionState, service_running: bool, profile: &str) -> Self {
+        TrayModel {
+            status: TrayStatus::new(state, service_running),
+            can_connect: service_running && state.can_connect(),
+            can_disconnect: service_running && state.can_disconnect(),
+            profile: profile.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "tray")]
+pub use self::service::{spawn_tray, TrayHandle};
+
+/// Stand-in when built without the `tray` feature, there is never a tray icon.
+#[cfg(not(feature = "tray"))]
+pub struct TrayHandle;
+
+#[cfg(not(feature = "tray"))]
+impl TrayHandle {
+    pub fn update(&self, _model: TrayModel) {}
+}
+
+#[cfg(not(feature = "tray"))]
+pub fn spawn_tray(
+    _model: TrayModel,
+    _actions: tokio::sync::mpsc::UnboundedSender<TrayAction>,
+) -> Option<TrayHandle> {
+    None
+}
+
+/// Status notifier item served over D-Bus by ksni.
+#[cfg(feature = "tray")]
+mod service {
+    use ksni::blocking::TrayMethods;
+    use ksni::menu::StandardItem;
+    use ksni::{MenuItem, ToolTip};
+    use log::{debug, warn};
+    use std::sync::mpsc;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    use super::{TrayAction, TrayModel};
+
+    struct SnxTray {
+        model: TrayModel,
+        actions: UnboundedSender<TrayAction>,
+    }
+
+    impl SnxTray {
+        fn item(label: String, enabled: bool, action: TrayAction) -> MenuItem<Self> {
+            StandardItem {
+                label,
+                enabled,
+                activate: Box::new(move |tray: &mut Self| tray.send(action)),
+                ..Default::default()
+            }
+            .into()
+        }
+
+        fn send(&self, action: TrayAction) {
+            debug!("Tray action {:?}", action);
+            if self.actions.send(action).is_err() {
+                warn!("Tray action {:?} dropped, the window is gone", action);
+            }
+        }
+    }
+
+    impl ksni::Tray for SnxTray {
+        fn id(&self) -> String {
+            "snx-rs-gui".into()
+        }
+
+        fn title(&self) -> String {
+            "snx-rs-gui".into()
+        }
+
+        fn icon_name(&self) -> String {
+            self.model.status.icon_name().into()
+        }
+
+        fn tool_tip(&self) -> ToolTip {
+            ToolTip {
+                title: "snx-rs-gui".into(),
+                description: format!("{} ({})", self.model.status, self.model.profile),
+                ..Default::default()
+            }
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayAction::ShowWindow);
+        }
+
+        fn menu(&self) -> Vec<MenuItem<Self>> {
+            let model = &self.model;
+            vec![
+                StandardItem {
+                    label: model.status.to_string(),
+                    enabled: false,
+                    ..Default::default()
+                }
+                .into(),
+                MenuItem::Separator,
+                Self::item(
+                    format!("Connect ({})", model.profile),
+                    model.can_connect,
+                    TrayAction::Connect,
+                ),
+                Self::item(
+                    "Disconnect".into(),
+                    model.can_disconnect,
+                    TrayAction::Disconnect,
+                ),
+                MenuItem::Separator,
+                Self::item("Show window".into(), true, TrayAction::ShowWindow),
+                Self::item("Quit".into(), true, TrayAction::Quit),
+            ]
+        }
+    }
+
+    /// Updates the running tray icon, in the order they are made.
+    pub struct TrayHandle {
+        updates: mpsc::Sender<TrayModel>,
+    }
+
+    impl TrayHandle {
+        pub fn update(&self, model: TrayModel) {
+            if self.updates.send(model).is_err() {
+                warn!("Tray is not running");
+            }
+        }
+    }
+
+    /// Shows the tray icon, `None` when the desktop has no status notifier host.
+    ///
+    /// Menu entries are sent to `actions`.
+    pub fn spawn_tray(
+        model: TrayModel,
+        actions: UnboundedSender<TrayAction>,
+    ) -> Option<TrayHandle> {
+        let handle = match (SnxTray { model, actions }).spawn() {
+            Ok(handle) => handle,
+            Err(e) => {
+                warn!("Cannot show tray icon: {}", e);
+                return None;
+            }
+        };
+        // The blocking handle must not be used from the async runtime of the window.
+        let (updates, receiver) = mpsc::channel::<TrayModel>();
+        std::thread::spawn(move || {
+            for model in receiver {
+                handle.update(|tray| tray.model = model);
+            }
+        });
+        Some(TrayHandle { updates })
+    }
+}
@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+use crate::certificate::{CertificateCache, Expiry};
+use crate::model::{same_domain, LoginType, TunnelParams};
+
+/// Longest domain name in presentation format, without the trailing dot.
+const MAX_DOMAIN_LENGTH: usize = 253;
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Setting an `Issue` belongs to, where the GUI shows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    ServerName,
+    UserName,
+    Password,
+    SearchDomains,
+    CaCert,
+    DefaultRoute,
+    NoCertCheck,
+}
+
+impl Field {
+    /// Whether the field is in the settings panel rather than the login form.
+    pub fn is_setting(&self) -> bool {
+        !matches!(self, Field::UserName | Field::Password)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Connecting works, but likely not as intended.
+    Warning,
+    /// The service would refuse the settings, connecting is blocked.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub field: Field,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Everything found wrong with a set of `TunnelParams`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validation {
+    pub issues: Vec<Issue>,
+}
+
+impl Validation {
+    fn error(&mut self, field: Field, message: impl Into<String>) {
+        self.issues.push(Issue {
+            field,
+            severity: Severity::Error,
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, field: Field, message: impl Into<String>) {
+        self.issues.push(Issue {
+            field,
+            severity: Severity::Warning,
+            message: message.into(),
+        });
+    }
+
+    pub fn field(&self, field: Field) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(move |issue| issue.field == field)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+    }
+
+    /// Whether connecting must be blocked.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+/// Checks `name` as a DNS name: dot-separated labels of letters, digits and inner hyphens.
+pub fn check_domain(name: &str) -> Result<(), String> {
+    let trimmed = name.strip_suffix('.').unwrap_or(name);
+    let valid = !trimmed.is_empty()
+        && trimmed.len() <= MAX_DOMAIN_LENGTH
+        && trimmed.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= MAX_LABEL_LENGTH
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    match valid {
+        true => Ok(()),
+        false => Err(format!("{} is not a valid domain name", name)),
+    }
+}
+
+/// Checks a gateway address: a host name or IP address with an optional port.
+pub fn check_server_name(server: &str) -> Result<(), String> {
+    let server = server.trim();
+    if server.is_empty() {
+        return Err("Server address is required".to_string());
+    }
+    if server.parse::<IpAddr>().is_ok() || server.parse::<SocketAddr>().is_ok() {
+        return Ok(());
+    }
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (server, None),
+    };
+    if let Some(port) = port {
+        if !port.parse::<u16>().is_ok_and(|port| port > 0) {
+            return Err(format!("{} is not a valid port", port));
+        }
+    }
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+    check_domain(host).map_err(|_| format!("{} is not a valid host name", host))
+}
+
+/// Checks `params` as they would be sent with `Connect`.
+///
+/// Only errors block connecting, warnings point out settings that are likely not intended.
+pub fn validate_params(params: &TunnelParams) -> Validation {
+    validate_params_at(params, Utc::now())
+}
+
+/// `validate_params` reading the CA files through `certificates`.
+pub fn validate_params_cached(
+    params: &TunnelParams,
+    certificates: &mut CertificateCache,
+) -> Validation {
+    check_params(params, Utc::now(), certificates)
+}
+
+/// `validate_params` with CA certificate expiry checked at `now`.
+pub fn validate_params_at(params: &TunnelParams, now: DateTime<Utc>) -> Validation {
+    check_params(params, now, &mut CertificateCache::default())
+}
+
+fn check_params(
+    params: &TunnelParams,
+    now: DateTime<Utc>,
+    cache: &mut CertificateCache,
+) -> Validation {
+    let mut validation = Validation::default();
+    if let Err(message) = check_server_name(&params.server_name) {
+        validation.error(Field::ServerName, message);
+    }
+    // The identity provider asks for the credentials of an SSO login.
+    if params.login_type != LoginType::SsoAzure {
+        if params.user_name.trim().is_empty() {
+            validation.error(Field::UserName, "Username is required");
+        }
+        if params.password.is_empty() {
+            validation.error(Field::Password, "Password is required");
+        }
+    }
+    let domains: Vec<&str> = params
+        .search_domains
+        .iter()
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .collect();
+    for (index, domain) in domains.iter().enumerate() {
+        if let Err(message) = check_domain(domain) {
+            validation.error(Field::SearchDomains, message);
+        }
+        if domains[..index].iter().any(|d| same_domain(d, domain)) {
+            validation.warning(Field::SearchDomains, format!("{} is listed twice", domain));
+        }
+    }
+    let ca_certs: Vec<&PathBuf> = params
+        .ca_cert
+        .iter()
+        .filter(|path| !path.as_os_str().is_empty())
+        .collect();
+    for path in &ca_certs {
+        let certificates = match cache.read(path) {
+            Ok(certificates) => certificates,
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                validation.error(
+                    Field::CaCert,
+                    format!("{} is not a certificate: {}", path.display(), e),
+                );
+                continue;
+            }
+            Err(e) => {
+                validation.error(
+                    Field::CaCert,
+                    format!("Cannot read {}: {}", path.display(), e),
+                );
+                continue;
+            }
+        };
+        for certificate in certificates {
+            let subject = &certificate.subject;
+            match certificate.expiry(now) {
+                Expiry::Valid => {}
+                Expiry::ExpiresSoon(days) => validation.warning(
+                    Field::CaCert,
+                    format!("{} expires in {} days", subject, days),
+                ),
+                Expiry::Expired => validation.warning(
+                    Field::CaCert,
+                    format!(
+                        "{} expired on {}",
+                        subject,
+                        certificate.not_after.format("%Y-%m-%d")
+                    ),
+                ),
+                Expiry::NotYetValid => validation.warning(
+                    Field::CaCert,
+                    format!(
+                        "{} is not valid before {}",
+                        subject,
+                        certificate.not_before.format("%Y-%m-%d")
+                    ),
+                ),
+            }
+        }
+    }
+    if params.no_cert_check && params.pinned_fingerprints.is_empty() {
+        validation.warning(
+            Field::NoCertCheck,
+            "No cert check accepts any server, pin the server certificate to be warned when it changes",
+        );
+    }
+    if !params.ca_cert.is_empty() && params.no_cert_check {
+        validation.warning(
+            Field::NoCertCheck,
+            "The CA certificates are not used while No cert check is on",
+        );
+    }
+    if params.default_route && params.no_routing {
+        validation.error(
+            Field::DefaultRoute,
+            "Default route and No routing exclude each other",
+        );
+    }
+    validation
+}
@@ -0,0 +1,205 @@
+use chrono::{Duration, TimeZone, Utc};
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use snx_rs_gui::certificate::{
+    bundle_ca_certs, parse_certificates, read_certificates, CertificateCache, Expiry,
+};
+use snx_rs_gui::file_chooser::file_uri_path;
+use snx_rs_gui::model::TunnelParams;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/certs")
+        .join(name)
+}
+
+#[test]
+fn reads_pem_certificate() {
+    let certificates = read_certificates(&fixture("corp-root-ca.pem")).unwrap();
+
+    assert_eq!(certificates.len(), 1);
+    let root = &certificates[0];
+    assert_eq!(
+        root.subject,
+        "C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA"
+    );
+    assert_eq!(root.issuer, root.subject);
+    assert_eq!(
+        root.not_before,
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        root.not_after,
+        Utc.with_ymd_and_hms(2044, 1, 1, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        root.to_string(),
+        "C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA, \
+         issued by C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA, valid until 2044-01-01"
+    );
+}
+
+#[test]
+fn reads_der_certificate() {
+    let certificates = read_certificates(&fixture("corp-old-ca.der")).unwrap();
+
+    assert_eq!(certificates.len(), 1);
+    assert_eq!(
+        certificates[0].subject,
+        "O=Example Corp, CN=Example Corp Old CA"
+    );
+    assert_eq!(
+        certificates[0].not_after,
+        Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn reads_every_certificate_of_a_bundle() {
+    let certificates = read_certificates(&fixture("corp-bundle.pem")).unwrap();
+
+    let subjects: Vec<&str> = certificates.iter().map(|c| c.subject.as_str()).collect();
+    assert_eq!(
+        subjects,
+        [
+            "C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA",
+            "CN=Example Corp Issuing CA, O=Example Corp",
+        ]
+    );
+    // Dates past 2049 are GeneralizedTime.
+    assert_eq!(
+        certificates[1].not_after,
+        Utc.with_ymd_and_hms(2052, 6, 1, 12, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn bundles_several_ca_files_into_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = dir.path().join("ca-bundle.pem");
+    let params = TunnelParams {
+        ca_cert: vec![fixture("corp-bundle.pem"), fixture("corp-old-ca.der")],
+        ..TunnelParams::default()
+    };
+
+    let params = bundle_ca_certs(params, &bundle).unwrap();
+
+    assert_eq!(params.ca_cert, [bundle.as_path()]);
+    let subjects: Vec<String> = read_certificates(&bundle)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.subject)
+        .collect();
+    assert_eq!(
+        subjects,
+        [
+            "C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA",
+            "CN=Example Corp Issuing CA, O=Example Corp",
+            "O=Example Corp, CN=Example Corp Old CA",
+        ]
+    );
+    let mode = std::fs::metadata(&bundle).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn single_ca_file_is_sent_as_is() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = dir.path().join("ca-bundle.pem");
+    let params = TunnelParams {
+        ca_cert: vec![fixture("corp-old-ca.der")],
+        ..TunnelParams::default()
+    };
+
+    assert_eq!(bundle_ca_certs(params.clone(), &bundle).unwrap(), params);
+    assert!(!bundle.exists());
+}
+
+#[test]
+fn unreadable_ca_files_are_not_bundled() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = dir.path().join("ca-bundle.pem");
+    let params = TunnelParams {
+        ca_cert: vec![fixture("corp-root-ca.pem"), fixture("missing.pem")],
+        ..TunnelParams::default()
+    };
+
+    let error = bundle_ca_certs(params, &bundle).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+    assert!(error.to_string().contains("missing.pem"));
+    assert!(!bundle.exists());
+}
+
+#[test]
+fn cache_reads_a_ca_file_again_once_it_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ca.pem");
+    std::fs::copy(fixture("corp-root-ca.pem"), &path).unwrap();
+    let mut cache = CertificateCache::default();
+    assert_eq!(cache.read(&path).unwrap().len(), 1);
+
+    // Same size and modification time: the cached certificates are kept.
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    let size = std::fs::metadata(&path).unwrap().len() as usize;
+    std::fs::write(&path, vec![b'x'; size]).unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(modified).unwrap();
+    assert_eq!(cache.read(&path).unwrap().len(), 1);
+
+    file.set_modified(modified + std::time::Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(
+        cache.read(&path).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+
+    std::fs::copy(fixture("corp-bundle.pem"), &path).unwrap();
+    assert_eq!(cache.read(&path).unwrap().len(), 2);
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cache.read(&path).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn rejects_files_without_certificates() {
+    let e = read_certificates(&fixture("not-a-cert.pem")).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+
+    let e = parse_certificates(&[0x30, 0x82, 0xff]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+
+    let e = read_certificates(&fixture("missing.pem")).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn expiry_states() {
+    let root = read_certificates(&fixture("corp-root-ca.pem"))
+        .unwrap()
+        .remove(0);
+    let at = |year, month, day| Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap();
+
+    assert_eq!(root.expiry(at(2023, 12, 31)), Expiry::NotYetValid);
+    assert_eq!(root.expiry(at(2030, 1, 1)), Expiry::Valid);
+    assert_eq!(
+        root.expiry(root.not_after - Duration::days(30)),
+        Expiry::ExpiresSoon(30)
+    );
+    assert_eq!(
+        root.expiry(root.not_after - Duration::hours(1)),
+        Expiry::ExpiresSoon(0)
+    );
+    assert_eq!(root.expiry(root.not_after), Expiry::Expired);
+}
+
+#[test]
+fn file_chooser_uris_become_paths() {
+    assert_eq!(
+        file_uri_path("file:///etc/ssl/certs/Corp%20CA.pem"),
+        Some(PathBuf::from("/etc/ssl/certs/Corp CA.pem"))
+    );
+    assert_eq!(file_uri_path("https://example.com/ca.pem"), None);
+    assert_eq!(file_uri_path("not a uri"), None);
+}
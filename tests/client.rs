@@ -0,0 +1,158 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use snx_rs_gui::client::{
+    check_service_address, resolve_service_address, CommandError, ConnectResponse, TunnelClient,
+};
+use snx_rs_gui::mock::{Behavior, MockServer};
+use snx_rs_gui::model::{TunnelParams, TunnelServiceRequest, TunnelServiceResponse};
+use snx_rs_gui::protocol::Protocol;
+
+async fn status_connected(client: &TunnelClient) -> bool {
+    match client.get_status().await.unwrap() {
+        TunnelServiceResponse::ConnectionStatus(status) => status.connected_since.is_some(),
+        other => panic!("unexpected response {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn connect_and_disconnect() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    assert!(!status_connected(&client).await);
+    client.connect(TunnelParams::default()).await.unwrap();
+    assert!(status_connected(&client).await);
+
+    client.disconnect().await.unwrap();
+    assert!(!status_connected(&client).await);
+    assert!(matches!(
+        server.requests()[..],
+        [
+            TunnelServiceRequest::GetStatus,
+            TunnelServiceRequest::Connect(_),
+            TunnelServiceRequest::GetStatus,
+            TunnelServiceRequest::Disconnect,
+            TunnelServiceRequest::GetStatus,
+        ]
+    ));
+}
+
+#[tokio::test]
+async fn rejected_connect_returns_service_error() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Reject("Wrong password".to_string())])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    let result = client.connect(TunnelParams::default()).await;
+    assert!(matches!(result, Err(CommandError::Rejected(msg)) if msg == "Wrong password"));
+    assert!(!server.is_connected());
+}
+
+#[tokio::test]
+async fn dropped_and_delayed_status_time_out() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_status([Behavior::Drop, Behavior::Delay(Duration::from_secs(1))])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr()).with_retries(0);
+
+    assert!(client.get_status().await.is_err());
+    assert!(client.get_status().await.is_err());
+    assert!(!status_connected(&client).await);
+}
+
+#[tokio::test]
+async fn connect_after_delay() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::ConnectAfter(Duration::from_millis(300))])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    client.connect(TunnelParams::default()).await.unwrap();
+    assert!(!status_connected(&client).await);
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(status_connected(&client).await);
+}
+
+#[tokio::test]
+async fn unanswered_disconnect_is_a_transport_error() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_disconnect([Behavior::Drop, Behavior::Drop, Behavior::Drop])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    let result = client.disconnect().await;
+    assert!(matches!(result, Err(CommandError::Transport(_))));
+}
+
+#[tokio::test]
+async fn connect_with_challenge_code() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Challenge("Enter OTP".to_string())])
+        .on_challenge([Behavior::Reject("Wrong code".to_string())])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr()).with_protocol(Protocol::Extended);
+
+    let response = client.connect(TunnelParams::default()).await.unwrap();
+    assert_eq!(
+        response,
+        ConnectResponse::Challenge("Enter OTP".to_string())
+    );
+    assert!(!status_connected(&client).await);
+
+    let result = client
+        .send_challenge_code("000000".to_string(), TunnelParams::default())
+        .await;
+    assert!(matches!(result, Err(CommandError::Rejected(msg)) if msg == "Wrong code"));
+
+    let response = client
+        .send_challenge_code("123456".to_string(), TunnelParams::default())
+        .await
+        .unwrap();
+    assert_eq!(response, ConnectResponse::Accepted);
+    assert!(status_connected(&client).await);
+}
+
+#[tokio::test]
+async fn service_addresses_are_checked_and_resolved() {
+    for valid in [
+        "127.0.0.1:7779",
+        "[::1]:7779",
+        " jump-host.example.com:7779 ",
+    ] {
+        assert!(check_service_address(valid).is_ok(), "{}", valid);
+    }
+    for invalid in ["", "127.0.0.1", "127.0.0.1:0", ":7779", "localhost:port"] {
+        let e = check_service_address(invalid).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput, "{}", invalid);
+    }
+
+    assert_eq!(
+        resolve_service_address("127.0.0.1:7779").await.unwrap(),
+        "127.0.0.1:7779".parse().unwrap()
+    );
+    let localhost = resolve_service_address("localhost:7779").await.unwrap();
+    assert!(localhost.ip().is_loopback());
+    assert_eq!(localhost.port(), 7779);
+    assert!(resolve_service_address("localhost").await.is_err());
+}
@@ -0,0 +1,343 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use snx_rs_gui::config::{migrate_legacy_config, read_config_from, save_config_to, CONFIG_VERSION};
+use snx_rs_gui::model::{LoginType, TunnelType, UserConfig};
+
+/// Copies a fixture into a fresh temporary directory, so backups do not touch the fixtures.
+fn fixture(name: &str) -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("user-config.json");
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/config")
+        .join(name);
+    fs::copy(source, &path).unwrap();
+    (dir, path)
+}
+
+fn backups(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "bak"))
+        .collect()
+}
+
+#[test]
+fn migrates_v1_into_default_profile() {
+    let (_dir, path) = fixture("v1.json");
+    let config = read_config_from(&path).unwrap();
+
+    assert_eq!(config.version, CONFIG_VERSION);
+    assert_eq!(config.profiles.len(), 1);
+    let profile = config.current_profile();
+    assert_eq!(profile.name, "Default");
+    assert!(!profile.save_password);
+    assert_eq!(profile.tunnel_params.server_name, "vpn.example.com");
+    assert_eq!(
+        profile.tunnel_params.search_domains,
+        ["example.com", "corp.example.com"]
+    );
+    assert_eq!(profile.tunnel_params.tunnel_type, TunnelType::Ipsec);
+    assert_eq!(config.service_address, "127.0.0.1:7779");
+    assert!(config.remember_me);
+}
+
+#[test]
+fn migrates_v1_with_service_address() {
+    let (_dir, path) = fixture("v1-service-address.json");
+    let config = read_config_from(&path).unwrap();
+
+    assert_eq!(config.service_address, "127.0.0.1:7780");
+    let params = &config.current_profile().tunnel_params;
+    assert_eq!(params.ca_cert, [Path::new("/etc/ssl/certs/corp-ca.pem")]);
+    assert_eq!(params.login_type, LoginType::Password);
+    // Older versions saved an empty domain for none.
+    assert!(params.search_domains.is_empty());
+}
+
+#[test]
+fn reads_v2_profiles() {
+    let (_dir, path) = fixture("v2.json");
+    let config = read_config_from(&path).unwrap();
+
+    assert_eq!(config.profiles.len(), 2);
+    assert_eq!(config.current_profile().name, "staging");
+    assert!(config.current_profile().tunnel_params.no_cert_check);
+    assert!(config.profile("prod").unwrap().save_password);
+    assert!(!config.profile("staging").unwrap().save_password);
+    assert!(config
+        .current_profile()
+        .tunnel_params
+        .search_domains
+        .is_empty());
+    assert_eq!(
+        config.profile("prod").unwrap().tunnel_params.search_domains,
+        ["example.com"]
+    );
+}
+
+#[test]
+fn reads_newer_version_with_defaults_and_keeps_a_copy() {
+    let (dir, path) = fixture("future.json");
+    let config = read_config_from(&path).unwrap();
+
+    assert_eq!(config.version, CONFIG_VERSION);
+    let params = &config.current_profile().tunnel_params;
+    assert_eq!(params.server_name, "vpn.example.com");
+    assert_eq!(params.log_level, "info");
+    assert!(params.reauth);
+    assert!(path.exists());
+    assert_eq!(backups(dir.path()).len(), 1);
+}
+
+#[test]
+fn unreadable_config_is_backed_up() {
+    let (dir, path) = fixture("truncated.json");
+
+    assert!(read_config_from(&path).is_none());
+    assert!(!path.exists());
+    let backups = backups(dir.path());
+    assert_eq!(backups.len(), 1);
+    assert_eq!(
+        fs::read(&backups[0]).unwrap(),
+        fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config/truncated.json")
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn unreadable_file_is_left_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    // Opening works, reading fails, as with a file the user has no permissions for.
+    let path = dir.path().join("user-config.json");
+    fs::create_dir(&path).unwrap();
+
+    assert!(read_config_from(&path).is_none());
+    assert!(path.is_dir());
+    assert!(backups(dir.path()).is_empty());
+}
+
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn config_is_written_atomically_and_privately() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_dir = dir.path().join("snx-rs-gui");
+    let path = config_dir.join("user-config.json");
+    let mut config = UserConfig::default();
+
+    save_config_to(&path, &config).unwrap();
+    config.current_params_mut().server_name = "vpn.example.com".to_string();
+    save_config_to(&path, &config).unwrap();
+
+    assert_eq!(mode(&config_dir), 0o700);
+    assert_eq!(mode(&path), 0o600);
+    let files: Vec<_> = fs::read_dir(&config_dir).unwrap().collect();
+    assert_eq!(files.len(), 1, "temporary files left: {:?}", files);
+    assert_eq!(
+        read_config_from(&path)
+            .unwrap()
+            .current_profile()
+            .tunnel_params
+            .server_name,
+        "vpn.example.com"
+    );
+}
+
+#[test]
+fn failed_write_keeps_the_old_config() {
+    let dir = tempfile::tempdir().unwrap();
+    // Renaming a file over a non-empty directory fails after the temporary file was written.
+    let path = dir.path().join("user-config.json");
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("keep"), "old").unwrap();
+
+    assert!(save_config_to(&path, &UserConfig::default()).is_err());
+
+    assert_eq!(fs::read_to_string(path.join("keep")).unwrap(), "old");
+    let files: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+    assert_eq!(files.len(), 1, "temporary files left: {:?}", files);
+}
+
+#[test]
+fn legacy_config_moves_to_the_config_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_legacy_dir, legacy_path) = fixture("v1.json");
+    let legacy = fs::read(&legacy_path).unwrap();
+    let path = dir.path().join("snx-rs-gui").join("user-config.json");
+
+    migrate_legacy_config(&legacy_path, &path).unwrap();
+
+    assert!(!legacy_path.exists());
+    assert_eq!(fs::read(&path).unwrap(), legacy);
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(mode(path.parent().unwrap()), 0o700);
+    assert_eq!(
+        read_config_from(&path)
+            .unwrap()
+            .current_profile()
+            .tunnel_params
+            .server_name,
+        "vpn.example.com"
+    );
+}
+
+#[test]
+fn legacy_config_does_not_replace_a_newer_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_legacy_dir, legacy_path) = fixture("v1.json");
+    let path = dir.path().join("user-config.json");
+    fs::write(&path, "{}").unwrap();
+
+    migrate_legacy_config(&legacy_path, &path).unwrap();
+    migrate_legacy_config(&dir.path().join("missing.json"), &path).unwrap();
+
+    assert!(legacy_path.exists());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+}
+
+#[test]
+fn saved_config_round_trips_without_passwords() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snx-rs-gui").join("user-config.json");
+    let mut config = UserConfig {
+        remember_me: true,
+        ..UserConfig::default()
+    };
+    config.current_params_mut().password = "secret".to_string();
+    config.current_params_mut().server_name = "vpn.example.com".to_string();
+
+    save_config_to(&path, &config).unwrap();
+    let saved = read_config_from(&path).unwrap();
+
+    assert_eq!(saved.version, CONFIG_VERSION);
+    assert_eq!(
+        saved.current_profile().tunnel_params.server_name,
+        "vpn.example.com"
+    );
+    assert_eq!(saved.current_profile().tunnel_params.password, "");
+}
+
+#[test]
+fn profiles_are_saved_without_remember_me() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("user-config.json");
+    let mut config = UserConfig::default();
+    config.current_params_mut().user_name = "alice".to_string();
+    config.create_profile("staging").unwrap();
+    config.current_params_mut().server_name = "vpn-staging.example.com".to_string();
+    config.current_params_mut().user_name = "alice".to_string();
+
+    save_config_to(&path, &config).unwrap();
+    let saved = read_config_from(&path).unwrap();
+
+    assert!(!saved.remember_me);
+    let names: Vec<&str> = saved.profiles.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Default", "staging"]);
+    assert_eq!(saved.current_profile().name, "staging");
+    let params = &saved.current_profile().tunnel_params;
+    assert_eq!(params.server_name, "vpn-staging.example.com");
+    assert!(saved
+        .profiles
+        .iter()
+        .all(|p| p.tunnel_params.user_name.is_empty()));
+
+    config.remember_me = true;
+    save_config_to(&path, &config).unwrap();
+    let saved = read_config_from(&path).unwrap();
+    assert!(saved
+        .profiles
+        .iter()
+        .all(|p| p.tunnel_params.user_name == "alice"));
+}
+
+#[test]
+fn create_and_duplicate_profiles() {
+    let mut config = UserConfig::default();
+    config.current_params_mut().server_name = "vpn.example.com".to_string();
+
+    config.create_profile("staging").unwrap();
+    assert_eq!(config.current_profile().name, "staging");
+    assert!(config
+        .current_profile()
+        .tunnel_params
+        .server_name
+        .is_empty());
+
+    config.duplicate_profile("Default", "Default copy").unwrap();
+    assert_eq!(config.current_profile().name, "Default copy");
+    assert_eq!(
+        config.current_profile().tunnel_params.server_name,
+        "vpn.example.com"
+    );
+    assert_eq!(config.unique_profile_name("staging"), "staging 2");
+
+    let e = config.create_profile("staging").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+    let e = config.create_profile("  ").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    let e = config.duplicate_profile("missing", "copy").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::NotFound);
+    assert_eq!(config.profiles.len(), 3);
+}
+
+#[test]
+fn rename_profile_keeps_it_selected() {
+    let mut config = UserConfig::default();
+    config.create_profile("staging").unwrap();
+
+    config.rename_profile("staging", "test").unwrap();
+    assert_eq!(config.last_profile.as_deref(), Some("test"));
+    assert_eq!(config.current_profile().name, "test");
+
+    config.rename_profile("Default", "prod").unwrap();
+    assert_eq!(config.current_profile().name, "test");
+    assert!(config.profile("Default").is_none());
+
+    let e = config.rename_profile("test", "prod").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+    let e = config.rename_profile("missing", "other").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn delete_profile_selects_another_one() {
+    let mut config = UserConfig::default();
+    config.create_profile("staging").unwrap();
+
+    config.delete_profile("staging").unwrap();
+    assert_eq!(config.profiles.len(), 1);
+    assert_eq!(config.last_profile.as_deref(), Some("Default"));
+
+    let e = config.delete_profile("Default").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    config.create_profile("staging").unwrap();
+    let e = config.delete_profile("missing").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn missing_last_profile_falls_back_to_the_first() {
+    let mut config = UserConfig::default();
+    config.create_profile("staging").unwrap();
+    config.last_profile = Some("deleted elsewhere".to_string());
+
+    assert_eq!(config.current_profile().name, "Default");
+    config.current_params_mut().server_name = "vpn.example.com".to_string();
+    assert_eq!(
+        config.profile("Default").unwrap().tunnel_params.server_name,
+        "vpn.example.com"
+    );
+    assert!(config.select_profile("deleted elsewhere").is_err());
+
+    config.profiles.clear();
+    config.ensure_profile();
+    assert_eq!(config.current_profile().name, "Default");
+}
@@ -0,0 +1,140 @@
+use snx_rs_gui::cli::{Action, CliArgs};
+use snx_rs_gui::client::TunnelClient;
+use snx_rs_gui::headless;
+use snx_rs_gui::mock::{Behavior, MockServer};
+use snx_rs_gui::model::{LoginType, Profile, TunnelServiceRequest, UserConfig};
+use snx_rs_gui::profiles::select_profile;
+use snx_rs_gui::protocol::Protocol;
+
+fn parse(args: &[&str]) -> Result<CliArgs, String> {
+    CliArgs::parse(args.iter().map(|a| a.to_string()))
+}
+
+fn no_code(prompt: &str) -> Result<String, std::io::Error> {
+    panic!("unexpected challenge {}", prompt)
+}
+
+#[test]
+fn parse_headless_actions() {
+    let args = parse(&["--connect", "--profile", "prod"]).unwrap();
+    assert_eq!(args.action, Some(Action::Connect));
+    assert_eq!(args.profile.as_deref(), Some("prod"));
+
+    let args = parse(&["--status", "--json", "--service-address=127.0.0.1:1"]).unwrap();
+    assert_eq!(args.action, Some(Action::Status));
+    assert!(args.json);
+
+    assert_eq!(parse(&[]).unwrap().action, None);
+    assert!(parse(&["--connect", "--disconnect"]).is_err());
+    assert!(parse(&["--status", "--profile=prod"]).is_err());
+    assert!(parse(&["--connect", "--json"]).is_err());
+    assert!(parse(&["--connect", "--profile"]).is_err());
+
+    let args = parse(&["--import", "corp.conf", "--profile", "corp"]).unwrap();
+    assert_eq!(args.action, Some(Action::Import));
+    assert_eq!(
+        args.file.as_deref(),
+        Some(std::path::Path::new("corp.conf"))
+    );
+    assert_eq!(args.profile.as_deref(), Some("corp"));
+    assert_eq!(
+        parse(&["--export", "corp.conf"]).unwrap().action,
+        Some(Action::Export)
+    );
+    assert!(parse(&["--export"]).is_err());
+    assert!(parse(&["--import", "a.conf", "--export", "b.conf"]).is_err());
+}
+
+#[tokio::test]
+async fn status_of_stopped_service() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_status([Behavior::Drop, Behavior::Drop, Behavior::Drop])
+        .spawn()
+        .unwrap();
+    let report = headless::status(&TunnelClient::new(server.local_addr())).await;
+    assert!(!report.service_running);
+    assert!(!report.connected);
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["service_running"], false);
+}
+
+#[tokio::test]
+async fn connect_profile_answering_challenge() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Challenge("Enter OTP".to_string())])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr()).with_protocol(Protocol::Extended);
+    let mut config = UserConfig::default();
+    config.create_profile("prod").unwrap();
+    config.profiles[1].tunnel_params.server_name = "vpn.example.com".to_string();
+    config.profiles[1].tunnel_params.user_name = "alice".to_string();
+    config.select_profile("Default").unwrap();
+    assert!(select_profile(&config, Some("missing")).is_err());
+
+    let profile = select_profile(&config, Some("prod")).unwrap();
+    let mut prompts = Vec::new();
+    let report = headless::connect(&client, profile, "secret".to_string(), |prompt| {
+        prompts.push(prompt.to_string());
+        Ok("123456\n".to_string())
+    })
+    .await
+    .unwrap();
+    assert!(report.connected);
+    assert_eq!(
+        report.details.server_name.as_deref(),
+        Some("vpn.example.com")
+    );
+    assert_eq!(prompts, ["Enter OTP"]);
+    assert!(server.requests().iter().any(|r| matches!(
+        r,
+        TunnelServiceRequest::ChallengeCode(code, params)
+            if code == "123456" && params.password == "secret"
+    )));
+
+    headless::disconnect(&client).await.unwrap();
+    assert!(!headless::status(&client).await.connected);
+}
+
+#[tokio::test]
+async fn connect_rejected_and_sso_unsupported() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Reject("Wrong password".to_string())])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+    let mut profile = Profile::default();
+    profile.tunnel_params.server_name = "vpn.example.com".to_string();
+    profile.tunnel_params.user_name = "alice".to_string();
+
+    let error = headless::connect(&client, &profile, "wrong".to_string(), no_code)
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Wrong password");
+
+    profile.tunnel_params.login_type = LoginType::SsoAzure;
+    assert!(headless::connect(&client, &profile, String::new(), no_code)
+        .await
+        .is_err());
+
+    // Until the service shows it speaks the extended protocol, MFA profiles are refused.
+    profile.tunnel_params.login_type = LoginType::PasswordWithMfa;
+    let error = headless::connect(&client, &profile, "secret".to_string(), no_code)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+
+    profile.tunnel_params.login_type = LoginType::Password;
+    profile.tunnel_params.server_name = "vpn example com".to_string();
+    let error = headless::connect(&client, &profile, "secret".to_string(), no_code)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,99 @@
+use snx_rs_gui::model::{NotificationSettings, UserConfig};
+use snx_rs_gui::notify::ConnectionNotification;
+use snx_rs_gui::state::ConnectionState;
+
+#[test]
+fn tunnel_coming_up_is_reported_once() {
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Connecting,
+            &ConnectionState::Connected,
+            true,
+            true,
+            Some("vpn.example.com"),
+        ),
+        Some(ConnectionNotification::TunnelUp {
+            server: Some("vpn.example.com".to_string())
+        })
+    );
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Connected,
+            &ConnectionState::Connected,
+            true,
+            true,
+            None,
+        ),
+        None
+    );
+}
+
+#[test]
+fn only_unexpected_drops_are_reported() {
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Connected,
+            &ConnectionState::Idle,
+            true,
+            true,
+            None,
+        ),
+        Some(ConnectionNotification::TunnelDropped)
+    );
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Disconnecting,
+            &ConnectionState::Idle,
+            true,
+            true,
+            None,
+        ),
+        None
+    );
+}
+
+#[test]
+fn stopped_service_wins_over_drop() {
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Connected,
+            &ConnectionState::Idle,
+            true,
+            false,
+            None,
+        ),
+        Some(ConnectionNotification::ServiceStopped)
+    );
+    assert_eq!(
+        ConnectionNotification::for_change(
+            &ConnectionState::Idle,
+            &ConnectionState::Idle,
+            false,
+            false,
+            None,
+        ),
+        None
+    );
+}
+
+#[test]
+fn settings_opt_out_per_event() {
+    let settings = NotificationSettings {
+        tunnel_dropped: false,
+        ..NotificationSettings::default()
+    };
+    assert!(!ConnectionNotification::TunnelDropped.is_enabled(&settings));
+    assert!(ConnectionNotification::ServiceStopped.is_enabled(&settings));
+    assert!(ConnectionNotification::TunnelUp { server: None }.is_enabled(&settings));
+}
+
+#[test]
+fn configs_without_notification_settings_enable_all() {
+    let config: UserConfig = serde_json::from_str(r#"{"version": 2, "profiles": []}"#).unwrap();
+    assert_eq!(config.notifications, NotificationSettings::default());
+
+    let config: UserConfig =
+        serde_json::from_str(r#"{"notifications": {"tunnel_up": false}}"#).unwrap();
+    assert!(!config.notifications.tunnel_up);
+    assert!(config.notifications.tunnel_dropped);
+}
@@ -0,0 +1,203 @@
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+use snx_rs_gui::client::TunnelClient;
+use snx_rs_gui::headless;
+use snx_rs_gui::mock::MockServer;
+use snx_rs_gui::model::{Profile, TunnelParams, TunnelServiceRequest};
+use snx_rs_gui::pinning::{
+    check_server, fetch_server_certificate, gateway_address, Fingerprint, ServerCheck,
+};
+
+/// `openssl x509 -in gateway.pem -noout -fingerprint -sha256`
+const GATEWAY_FINGERPRINT: &str =
+    "9D:5C:FC:C5:5D:F8:D8:E4:6B:3C:53:3B:70:3C:C8:35:6C:88:C1:0E:51:9F:BD:53:E6:9F:87:D9:2F:D6:40:A2";
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/certs")
+        .join(name)
+}
+
+/// Gateway with the self-signed `gateway.pem` certificate, answering TLS handshakes.
+async fn gateway() -> SocketAddr {
+    let certificate = CertificateDer::from_pem_file(fixture("gateway.pem")).unwrap();
+    let key = PrivateKeyDer::from_pem_file(fixture("gateway.key")).unwrap();
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            if let Ok(mut stream) = acceptor.accept(stream).await {
+                let _ = stream.shutdown().await;
+            }
+        }
+    });
+    address
+}
+
+fn params(address: SocketAddr, pins: Vec<Fingerprint>) -> TunnelParams {
+    TunnelParams {
+        server_name: address.to_string(),
+        pinned_fingerprints: pins,
+        ..TunnelParams::default()
+    }
+}
+
+#[test]
+fn fingerprints_read_and_write_colon_hex() {
+    let fingerprint: Fingerprint = GATEWAY_FINGERPRINT.parse().unwrap();
+    assert_eq!(fingerprint.to_string(), GATEWAY_FINGERPRINT);
+
+    let lowercase = GATEWAY_FINGERPRINT.replace(':', " ").to_lowercase();
+    assert_eq!(lowercase.parse::<Fingerprint>().unwrap(), fingerprint);
+
+    let json = serde_json::to_string(&fingerprint).unwrap();
+    assert_eq!(json, format!("\"{}\"", GATEWAY_FINGERPRINT));
+    assert_eq!(
+        serde_json::from_str::<Fingerprint>(&json).unwrap(),
+        fingerprint
+    );
+
+    for invalid in [
+        "",
+        "9D:5C",
+        "not a fingerprint",
+        &format!("{}:00", GATEWAY_FINGERPRINT),
+    ] {
+        let e = invalid.parse::<Fingerprint>().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput, "{}", invalid);
+    }
+}
+
+#[test]
+fn gateway_address_splits_host_and_port() {
+    let address = |host: &str, port| (host.to_string(), port);
+    assert_eq!(
+        gateway_address("vpn.example.com"),
+        address("vpn.example.com", 443)
+    );
+    assert_eq!(
+        gateway_address("vpn.example.com:8443"),
+        address("vpn.example.com", 8443)
+    );
+    assert_eq!(gateway_address("10.0.0.1"), address("10.0.0.1", 443));
+    assert_eq!(gateway_address("::1"), address("::1", 443));
+    assert_eq!(gateway_address("[::1]"), address("::1", 443));
+    assert_eq!(gateway_address("[::1]:8443"), address("::1", 8443));
+}
+
+#[tokio::test]
+async fn fetches_the_gateway_certificate() {
+    let address = gateway().await;
+
+    let certificate = fetch_server_certificate(&address.to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(certificate.server, address.to_string());
+    assert_eq!(certificate.fingerprint.to_string(), GATEWAY_FINGERPRINT);
+    let info = certificate.info.unwrap();
+    assert_eq!(info.subject, "O=Example Corp, CN=vpn.example.com");
+}
+
+#[tokio::test]
+async fn pinned_certificate_is_accepted() {
+    let address = gateway().await;
+    let pinned = params(address, vec![GATEWAY_FINGERPRINT.parse().unwrap()]);
+
+    let check = check_server(&pinned).await.unwrap();
+
+    assert!(matches!(check, ServerCheck::Pinned(_)), "{:?}", check);
+    check.verify().unwrap();
+}
+
+#[tokio::test]
+async fn changed_certificate_is_refused() {
+    let address = gateway().await;
+    let pinned = params(address, vec![Fingerprint::of(b"previous certificate")]);
+
+    let check = check_server(&pinned).await.unwrap();
+
+    assert!(matches!(check, ServerCheck::Mismatch(_)), "{:?}", check);
+    let e = check.verify().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+    assert!(e.to_string().contains(GATEWAY_FINGERPRINT), "{}", e);
+}
+
+#[tokio::test]
+async fn unpinned_certificate_is_offered() {
+    let address = gateway().await;
+    let unpinned = params(address, Vec::new());
+
+    let check = check_server(&unpinned).await.unwrap();
+
+    assert!(matches!(check, ServerCheck::Unpinned(_)), "{:?}", check);
+    assert_eq!(
+        check.certificate().fingerprint.to_string(),
+        GATEWAY_FINGERPRINT
+    );
+    check.verify().unwrap();
+}
+
+#[tokio::test]
+async fn unreachable_gateway_is_an_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert!(fetch_server_certificate(&address.to_string())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn headless_connect_checks_pins() {
+    let address = gateway().await;
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+    let mut profile = Profile {
+        tunnel_params: TunnelParams {
+            user_name: "alice".to_string(),
+            ..params(address, vec![Fingerprint::of(b"previous certificate")])
+        },
+        ..Profile::default()
+    };
+    let no_code = |_: &str| -> Result<String, std::io::Error> { panic!("no challenge expected") };
+
+    let e = headless::connect(&client, &profile, "secret".to_string(), no_code)
+        .await
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+    assert!(server.requests().is_empty());
+
+    profile.tunnel_params.pinned_fingerprints = vec![GATEWAY_FINGERPRINT.parse().unwrap()];
+    let report = headless::connect(&client, &profile, "secret".to_string(), no_code)
+        .await
+        .unwrap();
+    assert!(report.connected);
+    // The pins never turn the service's own certificate check off.
+    assert!(server.requests().iter().any(|r| matches!(
+        r,
+        TunnelServiceRequest::Connect(params) if !params.no_cert_check
+    )));
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use snx_rs_gui::model::Profile;
+use snx_rs_gui::profiles::profile_password;
+use snx_rs_gui::secrets::{MemorySecretStore, SecretStore};
+
+#[tokio::test]
+async fn password_from_store_or_fallback() {
+    let store = Arc::new(MemorySecretStore::default());
+    let mut profile = Profile::new("prod");
+    profile.tunnel_params.user_name = "alice".to_string();
+
+    let password = profile_password(&profile, Some(store.clone()), Some("env".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(password, "env");
+
+    store.set_password("prod", "alice", "saved").unwrap();
+    profile.save_password = true;
+    let password = profile_password(&profile, Some(store), None).await.unwrap();
+    assert_eq!(password, "saved");
+
+    assert!(profile_password(&profile, None, None).await.is_err());
+}
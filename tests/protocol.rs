@@ -0,0 +1,224 @@
+use serde_json::Value;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::net::UdpSocket;
+
+use snx_rs_gui::client::{ConnectResponse, TunnelClient};
+use snx_rs_gui::model::{
+    ConnectionStatus, LoginType, TunnelParams, TunnelServiceRequest, TunnelServiceResponse,
+    UserConfig,
+};
+use snx_rs_gui::pinning::Fingerprint;
+use snx_rs_gui::protocol::{Protocol, UnsupportedOption};
+
+fn fixture_path(protocol: Protocol, name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/protocol")
+        .join(protocol.to_string())
+        .join(name)
+}
+
+fn golden(protocol: Protocol, name: &str) -> Value {
+    let path = fixture_path(protocol, name);
+    let data = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    serde_json::from_slice(&data).unwrap()
+}
+
+fn params(login_type: LoginType) -> TunnelParams {
+    TunnelParams {
+        server_name: "vpn.example.com".to_string(),
+        user_name: "alice".to_string(),
+        password: "secret".to_string(),
+        search_domains: vec!["example.com".to_string()],
+        ca_cert: vec![PathBuf::from("/etc/ssl/certs/corp-ca.pem")],
+        login_type,
+        ..TunnelParams::default()
+    }
+}
+
+/// Requests with the golden file they are encoded to, for every protocol.
+fn requests() -> Vec<(&'static str, TunnelServiceRequest)> {
+    vec![
+        ("get_status.json", TunnelServiceRequest::GetStatus),
+        ("disconnect.json", TunnelServiceRequest::Disconnect),
+        (
+            "connect.json",
+            TunnelServiceRequest::Connect(params(LoginType::PasswordWithMsAuth)),
+        ),
+        (
+            "connect_sso.json",
+            TunnelServiceRequest::Connect(TunnelParams {
+                sso_token: Some("token-123".to_string()),
+                ..params(LoginType::SsoAzure)
+            }),
+        ),
+        (
+            "challenge_code.json",
+            TunnelServiceRequest::ChallengeCode(
+                "123456".to_string(),
+                params(LoginType::PasswordWithMfa),
+            ),
+        ),
+    ]
+}
+
+#[test]
+fn requests_match_golden_files() {
+    for protocol in Protocol::ALL {
+        for (name, request) in requests() {
+            let path = fixture_path(protocol, name);
+            match protocol.encode(&request) {
+                Ok(encoded) => {
+                    let encoded: Value = serde_json::from_slice(&encoded).unwrap();
+                    assert_eq!(encoded, golden(protocol, name), "{} {}", protocol, name);
+                }
+                Err(e) => {
+                    assert_eq!(e.kind(), ErrorKind::Unsupported, "{} {}", protocol, name);
+                    assert!(!path.exists(), "{} has a golden file", path.display());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn challenge_codes_need_the_extended_protocol() {
+    let request = TunnelServiceRequest::ChallengeCode(
+        "123456".to_string(),
+        params(LoginType::PasswordWithMfa),
+    );
+
+    let e = Protocol::Basic.encode(&request).unwrap_err();
+
+    assert_eq!(e.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn detects_protocol_from_connected_status() {
+    for protocol in Protocol::ALL {
+        let response: TunnelServiceResponse =
+            serde_json::from_value(golden(protocol, "status_connected.json")).unwrap();
+        let TunnelServiceResponse::ConnectionStatus(status) = response else {
+            panic!("{} status_connected.json is not a status", protocol);
+        };
+
+        assert_eq!(Protocol::detect(&status), Some(protocol));
+    }
+    assert_eq!(Protocol::detect(&ConnectionStatus::default()), None);
+}
+
+#[test]
+fn assumes_the_basic_protocol() {
+    assert_eq!(Protocol::default(), Protocol::Basic);
+    let client = TunnelClient::default();
+    assert_eq!(client.protocol(), Protocol::Basic);
+
+    let mfa = TunnelServiceRequest::ChallengeCode(
+        "123456".to_string(),
+        params(LoginType::PasswordWithMfa),
+    );
+    let e = client.protocol().encode(&mfa).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn reads_the_protocol_saved_by_earlier_builds() {
+    for (saved, protocol) in [("0.3", Protocol::Basic), ("0.4", Protocol::Extended)] {
+        let json = format!(r#"{{"protocol_version": "{}"}}"#, saved);
+        let config: UserConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(config.protocol, Some(protocol), "{}", saved);
+    }
+    let config: UserConfig = serde_json::from_str(r#"{"protocol": "basic"}"#).unwrap();
+    assert_eq!(config.protocol, Some(Protocol::Basic));
+}
+
+#[test]
+fn reports_unsupported_login_types() {
+    let basic = Protocol::Basic;
+
+    assert_eq!(
+        basic.unsupported_options(&params(LoginType::SsoAzure)),
+        [UnsupportedOption::SsoLogin]
+    );
+    assert_eq!(
+        basic.unsupported_options(&params(LoginType::PasswordWithMfa)),
+        [UnsupportedOption::ChallengeCodes]
+    );
+    assert!(basic
+        .unsupported_options(&params(LoginType::Password))
+        .is_empty());
+    for login_type in [LoginType::SsoAzure, LoginType::PasswordWithMfa] {
+        assert!(Protocol::Extended
+            .unsupported_options(&params(login_type))
+            .is_empty());
+    }
+}
+
+#[test]
+fn sends_a_single_ca_cert_path() {
+    let request = TunnelServiceRequest::Connect(params(LoginType::Password));
+    let no_cert = TunnelServiceRequest::Connect(TunnelParams {
+        ca_cert: Vec::new(),
+        ..params(LoginType::Password)
+    });
+    let unbundled = TunnelServiceRequest::Connect(TunnelParams {
+        ca_cert: vec![
+            PathBuf::from("/etc/ssl/certs/corp-ca.pem"),
+            PathBuf::from("/etc/ssl/certs/corp-old-ca.der"),
+        ],
+        ..params(LoginType::Password)
+    });
+
+    for protocol in Protocol::ALL {
+        let encoded: Value = serde_json::from_slice(&protocol.encode(&request).unwrap()).unwrap();
+        assert_eq!(
+            encoded["Connect"]["ca_cert"], "/etc/ssl/certs/corp-ca.pem",
+            "{}",
+            protocol
+        );
+        let encoded: Value = serde_json::from_slice(&protocol.encode(&no_cert).unwrap()).unwrap();
+        assert_eq!(encoded["Connect"]["ca_cert"], Value::Null, "{}", protocol);
+        let error = protocol.encode(&unbundled).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput, "{}", protocol);
+    }
+}
+
+#[test]
+fn leaves_out_pinned_fingerprints() {
+    let pinned = |login_type| TunnelParams {
+        pinned_fingerprints: vec![Fingerprint::of(b"gateway certificate")],
+        ..params(login_type)
+    };
+    let connect = TunnelServiceRequest::Connect(pinned(LoginType::PasswordWithMsAuth));
+    let challenge = TunnelServiceRequest::ChallengeCode(
+        "123456".to_string(),
+        pinned(LoginType::PasswordWithMfa),
+    );
+
+    for protocol in Protocol::ALL {
+        let encoded: Value = serde_json::from_slice(&protocol.encode(&connect).unwrap()).unwrap();
+        assert_eq!(encoded, golden(protocol, "connect.json"), "{}", protocol);
+    }
+    let encoded = Protocol::Extended.encode(&challenge).unwrap();
+    let encoded: Value = serde_json::from_slice(&encoded).unwrap();
+    assert_eq!(encoded, golden(Protocol::Extended, "challenge_code.json"));
+}
+
+#[tokio::test]
+async fn client_sends_the_wire_shape_of_its_protocol() {
+    let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let client = TunnelClient::new(server.local_addr().unwrap()).with_protocol(Protocol::Basic);
+    let sso_params = TunnelParams {
+        sso_token: Some("token-123".to_string()),
+        ..params(LoginType::SsoAzure)
+    };
+    let connect = tokio::spawn(async move { client.connect(sso_params).await });
+
+    let mut buf = vec![0u8; 65_507];
+    let (received, peer) = server.recv_from(&mut buf).await.unwrap();
+    server.send_to(br#""Ok""#, peer).await.unwrap();
+
+    let request: Value = serde_json::from_slice(&buf[..received]).unwrap();
+    assert_eq!(request, golden(Protocol::Basic, "connect_sso.json"));
+    assert_eq!(connect.await.unwrap().unwrap(), ConnectResponse::Accepted);
+}
@@ -0,0 +1,136 @@
+use chrono::Local;
+use std::time::Duration;
+
+use snx_rs_gui::model::ReconnectSettings;
+use snx_rs_gui::reconnect::{Reconnect, ReconnectPolicy};
+use snx_rs_gui::state::ConnectionState;
+
+fn enabled() -> ReconnectSettings {
+    ReconnectSettings {
+        enabled: true,
+        max_attempts: 3,
+        initial_delay_secs: 5,
+        max_delay_secs: 12,
+    }
+}
+
+#[test]
+fn delay_doubles_up_to_the_limit() {
+    let policy = ReconnectPolicy::from(&enabled());
+    assert_eq!(policy.delay(1), Some(Duration::from_secs(5)));
+    assert_eq!(policy.delay(2), Some(Duration::from_secs(10)));
+    assert_eq!(policy.delay(3), Some(Duration::from_secs(12)));
+    assert_eq!(policy.delay(4), None);
+
+    let policy = ReconnectPolicy {
+        max_attempts: u32::MAX,
+        ..policy
+    };
+    assert_eq!(policy.delay(200), Some(Duration::from_secs(12)));
+}
+
+#[test]
+fn drop_starts_countdown_only_when_enabled() {
+    let now = Local::now();
+    let next = Reconnect::Idle.on_state_change(
+        &enabled(),
+        &ConnectionState::Connected,
+        &ConnectionState::Idle,
+        now,
+    );
+    assert_eq!(
+        next,
+        Reconnect::Waiting {
+            attempt: 1,
+            at: now + chrono::Duration::seconds(5)
+        }
+    );
+
+    let next = Reconnect::Idle.on_state_change(
+        &ReconnectSettings::default(),
+        &ConnectionState::Connected,
+        &ConnectionState::Idle,
+        now,
+    );
+    assert_eq!(next, Reconnect::Idle);
+}
+
+#[test]
+fn user_disconnect_does_not_reconnect() {
+    let settings = enabled();
+    let now = Local::now();
+    let mut reconnect = Reconnect::Idle;
+    let mut state = ConnectionState::Connected;
+    for next in [ConnectionState::Disconnecting, ConnectionState::Idle] {
+        reconnect = reconnect.on_state_change(&settings, &state, &next, now);
+        state = next;
+    }
+    assert_eq!(reconnect, Reconnect::Idle);
+}
+
+#[test]
+fn failed_attempts_back_off_until_exhausted() {
+    let settings = enabled();
+    let now = Local::now();
+    let failed = ConnectionState::Failed("Wrong password".to_string());
+    let mut reconnect = Reconnect::Idle.on_state_change(
+        &settings,
+        &ConnectionState::Connected,
+        &ConnectionState::Idle,
+        now,
+    );
+    for attempt in 1..=3 {
+        assert!(matches!(reconnect, Reconnect::Waiting { attempt: a, .. } if a == attempt));
+        reconnect = reconnect.start_attempt();
+        assert_eq!(reconnect, Reconnect::Connecting { attempt });
+        reconnect = reconnect.on_state_change(
+            &settings,
+            &ConnectionState::Idle,
+            &ConnectionState::Connecting,
+            now,
+        );
+        reconnect =
+            reconnect.on_state_change(&settings, &ConnectionState::Connecting, &failed, now);
+    }
+    assert_eq!(reconnect, Reconnect::Idle);
+}
+
+#[test]
+fn connecting_or_cancelling_ends_reconnect() {
+    let settings = enabled();
+    let now = Local::now();
+    let waiting = Reconnect::Waiting {
+        attempt: 2,
+        at: now,
+    };
+    assert_eq!(
+        waiting.on_state_change(
+            &settings,
+            &ConnectionState::Idle,
+            &ConnectionState::Connected,
+            now
+        ),
+        Reconnect::Idle
+    );
+
+    let connecting = Reconnect::Connecting { attempt: 2 };
+    assert_eq!(
+        connecting.on_state_change(
+            &settings,
+            &ConnectionState::Connecting,
+            &ConnectionState::Connected,
+            now
+        ),
+        Reconnect::Idle
+    );
+    // Cancelling an MFA challenge goes back to idle.
+    assert_eq!(
+        connecting.on_state_change(
+            &settings,
+            &ConnectionState::Connecting,
+            &ConnectionState::Idle,
+            now
+        ),
+        Reconnect::Idle
+    );
+}
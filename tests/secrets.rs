@@ -0,0 +1,35 @@
+use snx_rs_gui::secrets::{move_password, MemorySecretStore, SecretStore};
+
+#[test]
+fn stores_passwords_per_profile_and_user() {
+    let store = MemorySecretStore::default();
+    store.set_password("prod", "alice", "secret").unwrap();
+
+    assert_eq!(
+        store.get_password("prod", "alice").unwrap().as_deref(),
+        Some("secret")
+    );
+    assert_eq!(store.get_password("staging", "alice").unwrap(), None);
+    assert_eq!(store.get_password("prod", "bob").unwrap(), None);
+
+    store.delete_password("prod", "alice").unwrap();
+    store.delete_password("prod", "alice").unwrap();
+    assert_eq!(store.get_password("prod", "alice").unwrap(), None);
+}
+
+#[test]
+fn move_password_follows_profile_rename() {
+    let store = MemorySecretStore::default();
+    store.set_password("prod", "alice", "secret").unwrap();
+
+    move_password(&store, "prod", "production", "alice").unwrap();
+
+    assert_eq!(store.get_password("prod", "alice").unwrap(), None);
+    assert_eq!(
+        store
+            .get_password("production", "alice")
+            .unwrap()
+            .as_deref(),
+        Some("secret")
+    );
+}
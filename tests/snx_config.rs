@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use snx_rs_gui::config::{read_config_from, save_config_to};
+use snx_rs_gui::model::{LoginType, TunnelParams, TunnelType, UserConfig};
+use snx_rs_gui::profiles::{export_profile, import_profile};
+use snx_rs_gui::snx_config::{
+    export_snx_config, import_snx_config, read_snx_config, UnmappedKey, UnmappedReason,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/snx-rs")
+        .join(name)
+}
+
+#[test]
+fn imports_mapped_keys() {
+    let import = read_snx_config(&fixture("corp.conf")).unwrap();
+    let params = import.params;
+
+    assert_eq!(params.server_name, "vpn.example.com");
+    assert_eq!(params.user_name, "alice");
+    assert!(params.password.is_empty());
+    assert_eq!(params.login_type, LoginType::PasswordWithMsAuth);
+    assert_eq!(params.tunnel_type, TunnelType::Ipsec);
+    assert_eq!(params.log_level, "debug");
+    assert_eq!(params.search_domains, ["example.com", "corp.example.com"]);
+    assert!(params.no_dns);
+    assert!(!params.no_cert_check);
+    assert_eq!(params.ca_cert, [Path::new("/etc/ssl/certs/corp-ca.pem")]);
+}
+
+#[test]
+fn reports_unmapped_keys() {
+    let import = read_snx_config(&fixture("corp.conf")).unwrap();
+
+    let unmapped = |line: usize, key: &str, reason: UnmappedReason| UnmappedKey {
+        line,
+        key: key.to_string(),
+        reason,
+    };
+    assert_eq!(
+        import.unmapped,
+        [
+            unmapped(4, "password", UnmappedReason::Password),
+            unmapped(
+                12,
+                "no-cert-check",
+                UnmappedReason::InvalidValue("maybe".to_string())
+            ),
+            unmapped(14, "ignore-server-cert", UnmappedReason::Unsupported),
+            unmapped(15, "add-routes", UnmappedReason::Unsupported),
+            unmapped(16, "this line has no value", UnmappedReason::Malformed),
+        ]
+    );
+    assert_eq!(
+        import.unmapped[1].to_string(),
+        "line 12: no-cert-check: invalid value maybe"
+    );
+}
+
+#[test]
+fn export_round_trips() {
+    let params = TunnelParams {
+        server_name: "vpn.example.com".to_string(),
+        user_name: "alice".to_string(),
+        password: "secret".to_string(),
+        log_level: "warn".to_string(),
+        reauth: false,
+        search_domains: vec!["example.com".to_string(), "corp.example.com".to_string()],
+        default_route: true,
+        no_dns: true,
+        tunnel_type: TunnelType::Ipsec,
+        ca_cert: vec![
+            PathBuf::from("/etc/ssl/certs/corp-ca.pem"),
+            PathBuf::from("/etc/ssl/certs/corp-old-ca.der"),
+        ],
+        login_type: LoginType::SsoAzure,
+        ..TunnelParams::default()
+    };
+
+    let exported = export_snx_config(&params);
+    let import = import_snx_config(&exported);
+
+    assert!(!exported.contains("secret"), "{}", exported);
+    assert!(import.unmapped.is_empty(), "{:?}", import.unmapped);
+    assert_eq!(
+        serde_json::to_value(&import.params).unwrap(),
+        serde_json::to_value(TunnelParams {
+            password: String::new(),
+            ..params
+        })
+        .unwrap()
+    );
+}
+
+#[test]
+fn invalid_search_domains_are_reported() {
+    let import = import_snx_config("search-domains=example.com,not a domain\n");
+
+    assert_eq!(
+        import.unmapped,
+        [UnmappedKey {
+            line: 1,
+            key: "search-domains".to_string(),
+            reason: UnmappedReason::InvalidValue("example.com,not a domain".to_string()),
+        }]
+    );
+    assert!(import.params.search_domains.is_empty());
+}
+
+#[test]
+fn empty_file_keeps_defaults() {
+    let import = import_snx_config("# nothing here\n\n");
+
+    assert!(import.unmapped.is_empty());
+    assert_eq!(
+        serde_json::to_value(&import.params).unwrap(),
+        serde_json::to_value(TunnelParams::default()).unwrap()
+    );
+    assert!(!export_snx_config(&import.params).contains("search-domains"));
+}
+
+#[test]
+fn imports_into_new_profile() {
+    let mut config = UserConfig::default();
+
+    let (name, _) = import_profile(&mut config, &fixture("corp.conf"), None).unwrap();
+    let (second, _) = import_profile(&mut config, &fixture("corp.conf"), None).unwrap();
+
+    assert_eq!(name, "corp");
+    assert_eq!(second, "corp 2");
+    assert_eq!(config.current_profile().name, "corp 2");
+    assert_eq!(
+        config.current_profile().tunnel_params.server_name,
+        "vpn.example.com"
+    );
+    assert!(import_profile(&mut config, &fixture("corp.conf"), Some("corp")).is_err());
+}
+
+#[test]
+fn imported_profile_survives_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("user-config.json");
+    // As `--import` finds it on a fresh install, without remember me.
+    let mut config = UserConfig::default();
+    assert!(!config.remember_me);
+
+    let (name, _) = import_profile(&mut config, &fixture("corp.conf"), None).unwrap();
+    save_config_to(&path, &config).unwrap();
+    let reloaded = read_config_from(&path).unwrap();
+
+    let names: Vec<&str> = reloaded.profiles.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Default", "corp"]);
+    assert_eq!(reloaded.current_profile().name, name);
+    let params = &reloaded.current_profile().tunnel_params;
+    assert_eq!(params.server_name, "vpn.example.com");
+    assert_eq!(params.search_domains, ["example.com", "corp.example.com"]);
+}
+
+#[test]
+fn exports_named_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corp.conf");
+    let mut config = UserConfig::default();
+    config.current_params_mut().server_name = "vpn.example.com".to_string();
+
+    export_profile(&config, &path, Some("Default")).unwrap();
+
+    let import = read_snx_config(&path).unwrap();
+    assert_eq!(import.params.server_name, "vpn.example.com");
+    assert!(export_profile(&config, &path, Some("missing")).is_err());
+}
@@ -0,0 +1,194 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use url::Url;
+
+use snx_rs_gui::client::{ConnectResponse, TunnelClient};
+use snx_rs_gui::mock::{Behavior, MockIdentityProvider, MockServer};
+use snx_rs_gui::model::{LoginType, TunnelParams, TunnelServiceRequest};
+use snx_rs_gui::protocol::Protocol;
+use snx_rs_gui::sso::{token_from_redirect, RedirectListener, SSO_REDIRECT_ADDRESS};
+
+/// Minimal HTTP GET standing in for the login window, returns the status line and the `Location` header.
+async fn get(url: &str) -> (String, Option<String>) {
+    let url = Url::parse(url).unwrap();
+    let address = format!("{}:{}", url.host_str().unwrap(), url.port().unwrap());
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let mut lines = response.lines();
+    let status = lines.next().unwrap().to_string();
+    let location = lines
+        .take_while(|l| !l.is_empty())
+        .find_map(|l| l.strip_prefix("Location: "))
+        .map(str::to_string);
+    (status, location)
+}
+
+#[tokio::test]
+async fn sso_login_with_mock_identity_provider() {
+    let listener = RedirectListener::bind("127.0.0.1:0").await.unwrap();
+    let idp = MockIdentityProvider::bind("127.0.0.1:0", "secret-token")
+        .await
+        .unwrap();
+    let login_url = idp.login_url().unwrap();
+    idp.spawn().unwrap();
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Sso(login_url.clone())])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr()).with_protocol(Protocol::Extended);
+    let params = TunnelParams {
+        login_type: LoginType::SsoAzure,
+        ..TunnelParams::default()
+    };
+
+    let response = client.connect(params.clone()).await.unwrap();
+    assert_eq!(
+        response,
+        ConnectResponse::IdentityProvider(login_url.clone())
+    );
+
+    let window_url = listener.login_url(&login_url).unwrap();
+    let (status, _) = get(&window_url).await;
+    assert!(status.contains("200"), "{}", status);
+    let mut authorize = Url::parse(&window_url).unwrap();
+    authorize.set_path("/authorize");
+    let (status, location) = get(authorize.as_str()).await;
+    assert!(status.contains("302"), "{}", status);
+    let location = location.unwrap();
+    assert!(
+        location.starts_with(&listener.redirect_url().unwrap()),
+        "{}",
+        location
+    );
+    let (token, (status, _)) = tokio::join!(listener.accept_token(), get(&location));
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(token.unwrap(), "secret-token");
+
+    let response = client
+        .connect(TunnelParams {
+            sso_token: Some("secret-token".to_string()),
+            ..params
+        })
+        .await
+        .unwrap();
+    assert_eq!(response, ConnectResponse::Accepted);
+    assert!(server.is_connected());
+    assert!(matches!(
+        &server.requests()[..],
+        [
+            TunnelServiceRequest::Connect(TunnelParams { sso_token: None, .. }),
+            TunnelServiceRequest::Connect(TunnelParams { sso_token: Some(token), .. }),
+        ] if token == "secret-token"
+    ));
+}
+
+/// The redirect the identity provider sends the login window to with `token`.
+fn redirect(listener: &RedirectListener, token: &str) -> String {
+    let login = Url::parse(&listener.login_url("http://idp.example.com/login").unwrap()).unwrap();
+    let state = query(&login, "state").unwrap();
+    let mut redirect = Url::parse(&query(&login, "redirect_uri").unwrap()).unwrap();
+    redirect
+        .query_pairs_mut()
+        .append_pair("token", token)
+        .append_pair("state", &state);
+    redirect.into()
+}
+
+fn query(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+#[tokio::test]
+async fn login_url_carries_redirect_and_state() {
+    let listener = RedirectListener::bind(SSO_REDIRECT_ADDRESS).await.unwrap();
+    let other = RedirectListener::bind(SSO_REDIRECT_ADDRESS).await.unwrap();
+
+    let login = Url::parse(
+        &listener
+            .login_url("https://login.example.com/saml?tenant=corp")
+            .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(query(&login, "tenant").as_deref(), Some("corp"));
+    let redirect_uri = query(&login, "redirect_uri").unwrap();
+    assert_eq!(redirect_uri, listener.redirect_url().unwrap());
+    let state = query(&login, "state").unwrap();
+    assert_eq!(state.len(), 32);
+    let other_login =
+        Url::parse(&other.login_url("https://login.example.com/saml").unwrap()).unwrap();
+    assert_ne!(query(&other_login, "state").unwrap(), state);
+}
+
+#[tokio::test]
+async fn redirect_listener_ignores_other_paths_and_states() {
+    let listener = RedirectListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let redirect = redirect(&listener, "abc def");
+    let browser = async {
+        let (status, _) = get(&format!("http://{}/favicon.ico", address)).await;
+        assert!(status.contains("404"), "{}", status);
+        let (status, _) = get(&format!("http://{}/sso?token=injected", address)).await;
+        assert!(status.contains("400"), "{}", status);
+        let (status, _) = get(&format!(
+            "http://{}/sso?token=injected&state=guess",
+            address
+        ))
+        .await;
+        assert!(status.contains("400"), "{}", status);
+        get(&redirect).await
+    };
+    let (token, _) = tokio::join!(listener.accept_token(), browser);
+    assert_eq!(token.unwrap(), "abc def");
+}
+
+#[tokio::test]
+async fn stalled_and_oversized_requests_do_not_block_the_login() {
+    let listener = RedirectListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let redirect = redirect(&listener, "token");
+    let browser = async {
+        // Connects and never sends a request.
+        let _stalled = TcpStream::connect(address).await.unwrap();
+        let mut flood = TcpStream::connect(address).await.unwrap();
+        // Writing may fail once the listener gives up on the request.
+        let _ = flood.write_all(&vec![b'A'; 64 * 1024]).await;
+        let mut response = Vec::new();
+        let _ = flood.read_to_end(&mut response).await;
+        assert!(response.is_empty());
+        get(&redirect).await
+    };
+
+    let (token, _) = timeout(Duration::from_secs(5), async {
+        tokio::join!(listener.accept_token(), browser)
+    })
+    .await
+    .unwrap();
+    assert_eq!(token.unwrap(), "token");
+}
+
+#[test]
+fn redirect_with_error() {
+    let url = Url::parse(
+        "http://127.0.0.1:7780/sso?error=access_denied&error_description=User+cancelled",
+    )
+    .unwrap();
+    let error = token_from_redirect(&url).unwrap_err();
+    assert_eq!(error.to_string(), "SSO login failed: User cancelled");
+
+    let url = Url::parse("http://127.0.0.1:7780/sso").unwrap();
+    assert!(token_from_redirect(&url).is_err());
+}
@@ -0,0 +1,135 @@
+use snx_rs_gui::state::{ConnectionEvent as E, ConnectionState as S};
+
+fn failed(message: &str) -> S {
+    S::Failed(message.to_string())
+}
+
+/// Runs `events` from `start`, returning every state passed through.
+fn run(start: S, events: &[E]) -> Vec<S> {
+    events
+        .iter()
+        .scan(start, |state, event| {
+            *state = state.next(event);
+            Some(state.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn connecting_until_the_tunnel_is_up() {
+    assert_eq!(
+        run(
+            S::Idle,
+            &[
+                E::ConnectRequested,
+                E::ConnectSucceeded,
+                E::StatusPolled(false),
+                E::ServiceDown,
+                E::StatusPolled(true),
+            ]
+        ),
+        [
+            S::Connecting,
+            S::Connecting,
+            S::Connecting,
+            S::Connecting,
+            S::Connected
+        ]
+    );
+}
+
+#[test]
+fn connecting_fails() {
+    let connecting = S::Idle.next(&E::ConnectRequested);
+
+    assert_eq!(
+        connecting.next(&E::ConnectFailed("Wrong password".to_string())),
+        failed("Wrong password")
+    );
+    assert_eq!(
+        connecting.next(&E::ConnectTimedOut),
+        failed("Timed out waiting for the tunnel to come up")
+    );
+    assert_eq!(connecting.next(&E::ConnectCancelled), S::Idle);
+    // A failed attempt can be retried.
+    assert_eq!(
+        failed("Wrong password").next(&E::ConnectRequested),
+        S::Connecting
+    );
+}
+
+#[test]
+fn disconnecting_until_the_tunnel_is_down() {
+    assert_eq!(
+        run(
+            S::Connected,
+            &[
+                E::DisconnectRequested,
+                E::StatusPolled(true),
+                E::DisconnectSucceeded
+            ]
+        ),
+        [S::Disconnecting, S::Disconnecting, S::Idle]
+    );
+    assert_eq!(S::Disconnecting.next(&E::StatusPolled(false)), S::Idle);
+    assert_eq!(
+        S::Disconnecting.next(&E::DisconnectFailed("timeout".to_string())),
+        S::Connected
+    );
+    // Disconnecting while connecting cancels the attempt.
+    assert_eq!(
+        S::Connecting.next(&E::DisconnectRequested),
+        S::Disconnecting
+    );
+}
+
+#[test]
+fn stale_replies_are_ignored() {
+    // Answers to commands that were cancelled or superseded meanwhile.
+    for state in [S::Idle, S::Connected, failed("Wrong password")] {
+        for event in [
+            E::ConnectSucceeded,
+            E::ConnectFailed("late".to_string()),
+            E::ConnectTimedOut,
+            E::ConnectCancelled,
+            E::DisconnectSucceeded,
+            E::DisconnectFailed("late".to_string()),
+        ] {
+            assert_eq!(state.next(&event), state, "{:?} after {:?}", event, state);
+        }
+    }
+    assert_eq!(
+        S::Disconnecting.next(&E::ConnectSucceeded),
+        S::Disconnecting
+    );
+    assert_eq!(S::Connecting.next(&E::DisconnectSucceeded), S::Connecting);
+    assert_eq!(
+        S::Disconnecting.next(&E::ConnectRequested),
+        S::Disconnecting
+    );
+    assert_eq!(S::Connected.next(&E::ConnectRequested), S::Connected);
+}
+
+#[test]
+fn polls_follow_tunnels_changed_elsewhere() {
+    let up = S::Idle.next(&E::StatusPolled(true));
+    assert_eq!(up, S::Connected);
+    assert_eq!(failed("x").next(&E::StatusPolled(true)), S::Connected);
+
+    let dropped = S::Connected.next(&E::StatusPolled(false));
+    assert_eq!(dropped, S::Idle);
+    assert!(S::Connected.is_drop(&dropped));
+    assert!(S::Connected.is_drop(&S::Connected.next(&E::ServiceDown)));
+    assert!(!S::Disconnecting.is_drop(&S::Idle));
+}
+
+#[test]
+fn buttons_follow_the_state() {
+    assert!(S::Idle.can_connect() && !S::Idle.can_disconnect());
+    assert!(failed("x").can_connect());
+    assert!(!S::Connecting.can_connect() && S::Connecting.can_disconnect());
+    assert!(!S::Connected.can_connect() && S::Connected.can_disconnect());
+    assert!(!S::Disconnecting.can_connect() && !S::Disconnecting.can_disconnect());
+    assert!(S::Connecting.is_busy() && S::Disconnecting.is_busy());
+    assert!(!S::Connected.is_busy());
+}
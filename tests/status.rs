@@ -0,0 +1,106 @@
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+
+use snx_rs_gui::model::{ConnectionStatus, TunnelServiceResponse, TunnelType};
+use snx_rs_gui::status::{connection_details, format_duration};
+
+fn status(connected_since: Option<&str>) -> ConnectionStatus {
+    ConnectionStatus {
+        connected_since: connected_since.map(str::to_string),
+        ..ConnectionStatus::default()
+    }
+}
+
+fn utc(time: DateTime<Local>) -> DateTime<Utc> {
+    time.with_timezone(&Utc)
+}
+
+#[test]
+fn connected_since_formats() {
+    let expected = Utc.with_ymd_and_hms(2024, 3, 1, 8, 30, 0).unwrap();
+
+    let rfc3339 = status(Some("2024-03-01T09:30:00+01:00"));
+    assert_eq!(rfc3339.connected_since_time().map(utc), Some(expected));
+    // chrono's `Display`, as written by some snx-rs versions.
+    let display = status(Some("2024-03-01 08:30:00.250 +00:00"));
+    assert_eq!(
+        display.connected_since_time().map(utc),
+        Some(expected + Duration::milliseconds(250))
+    );
+
+    assert_eq!(status(Some("yesterday")).connected_since_time(), None);
+    assert!(status(Some("yesterday")).is_connected());
+    assert_eq!(status(None).connected_since_time(), None);
+    assert!(!status(None).is_connected());
+}
+
+#[test]
+fn session_duration_since_connecting() {
+    let connected = status(Some("2024-03-01T08:30:00Z"));
+    let now = Utc
+        .with_ymd_and_hms(2024, 3, 2, 10, 31, 5)
+        .unwrap()
+        .with_timezone(&Local);
+
+    let duration = connected.session_duration(now).unwrap();
+    assert_eq!(format_duration(duration), "1d 02:01:05");
+    // A clock running behind the service does not make the session negative.
+    let before = connected.session_duration(now - Duration::days(2)).unwrap();
+    assert_eq!(before, Duration::zero());
+    assert_eq!(status(None).session_duration(now), None);
+}
+
+#[test]
+fn durations_are_formatted() {
+    assert_eq!(format_duration(Duration::zero()), "00:00:00");
+    assert_eq!(format_duration(Duration::seconds(59)), "00:00:59");
+    assert_eq!(format_duration(Duration::seconds(3_661)), "01:01:01");
+    assert_eq!(
+        format_duration(Duration::hours(23) + Duration::minutes(59)),
+        "23:59:00"
+    );
+    assert_eq!(format_duration(Duration::days(3)), "3d 00:00:00");
+    assert_eq!(format_duration(Duration::seconds(-5)), "00:00:00");
+}
+
+fn parse(json: &str) -> Option<ConnectionStatus> {
+    connection_details(serde_json::from_str::<TunnelServiceResponse>(json).unwrap())
+}
+
+#[test]
+fn older_services_report_only_connected_since() {
+    let details =
+        parse(r#"{"ConnectionStatus":{"connected_since":"2024-03-01T08:30:00Z"}}"#).unwrap();
+
+    assert!(details.connected_since_time().is_some());
+    assert_eq!(details.server_name, None);
+    assert_eq!(details.tunnel_type, None);
+    assert_eq!(details.ip_address, None);
+    assert_eq!(details.dns_servers, None);
+    assert_eq!(details.routes, None);
+
+    assert!(parse(r#"{"ConnectionStatus":{"connected_since":null}}"#).is_none());
+    assert!(parse(r#""Ok""#).is_some());
+    assert!(parse(r#"{"Error":"not running"}"#).is_none());
+}
+
+#[test]
+fn newer_services_report_tunnel_details() {
+    let details = parse(
+        r#"{"ConnectionStatus":{
+            "connected_since":"2024-03-01T08:30:00Z",
+            "server_name":"vpn.example.com",
+            "tunnel_type":"Ipsec",
+            "ip_address":"10.0.0.2",
+            "dns_servers":["10.0.0.53"],
+            "routes":["10.0.0.0/8"],
+            "some_future_field":true
+        }}"#,
+    )
+    .unwrap();
+
+    assert_eq!(details.server_name.as_deref(), Some("vpn.example.com"));
+    assert_eq!(details.tunnel_type, Some(TunnelType::Ipsec));
+    assert_eq!(details.ip_address.as_deref(), Some("10.0.0.2"));
+    assert_eq!(details.dns_servers.unwrap(), ["10.0.0.53"]);
+    assert_eq!(details.routes.unwrap(), ["10.0.0.0/8"]);
+}
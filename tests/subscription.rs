@@ -0,0 +1,135 @@
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::time::sleep;
+
+use snx_rs_gui::model::TunnelServiceResponse;
+use snx_rs_gui::state::ConnectionState;
+use snx_rs_gui::subscription::{
+    watch_status, PollPolicy, StatusSource, StatusSubscription, WatchHints,
+};
+
+/// Counts fetches, and resolves `changed` whenever something is sent on `pushes`.
+struct FakeSource {
+    fetches: Arc<Mutex<u32>>,
+    pushes: Option<UnboundedReceiver<()>>,
+}
+
+impl StatusSource for FakeSource {
+    async fn fetch(&mut self) -> Result<TunnelServiceResponse, Error> {
+        *self.fetches.lock().unwrap() += 1;
+        Ok(TunnelServiceResponse::Ok)
+    }
+
+    async fn changed(&mut self) {
+        match &mut self.pushes {
+            Some(pushes) => {
+                pushes.recv().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+fn spawn_watch(
+    subscription: &StatusSubscription,
+    pushes: Option<UnboundedReceiver<()>>,
+) -> Arc<Mutex<u32>> {
+    let fetches = Arc::new(Mutex::new(0));
+    let source = FakeSource {
+        fetches: fetches.clone(),
+        pushes,
+    };
+    let subscription = subscription.clone();
+    tokio::spawn(async move {
+        watch_status(source, PollPolicy::default(), &subscription, |_| {}).await;
+    });
+    fetches
+}
+
+fn hints(state: ConnectionState, visible: bool, background: bool) -> WatchHints {
+    WatchHints {
+        state,
+        service_running: true,
+        visible,
+        background,
+    }
+}
+
+#[test]
+fn interval_follows_connection_and_window() {
+    let policy = PollPolicy::default();
+    let interval = |h: &WatchHints| policy.interval(h);
+    assert_eq!(
+        interval(&hints(ConnectionState::Connecting, true, false)),
+        Some(policy.busy)
+    );
+    assert_eq!(
+        interval(&hints(ConnectionState::Connected, true, false)),
+        Some(policy.connected)
+    );
+    assert_eq!(
+        interval(&hints(ConnectionState::Idle, true, false)),
+        Some(policy.idle)
+    );
+    assert_eq!(
+        interval(&hints(ConnectionState::Connected, false, false)),
+        None
+    );
+    assert_eq!(
+        interval(&hints(ConnectionState::Connected, false, true)),
+        Some(policy.background)
+    );
+    // Commands started from the tray finish while hidden.
+    assert_eq!(
+        interval(&hints(ConnectionState::Disconnecting, false, false)),
+        Some(policy.busy)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn polls_faster_while_busy() {
+    let subscription = StatusSubscription::default();
+    let fetches = spawn_watch(&subscription, None);
+
+    sleep(Duration::from_millis(9500)).await;
+    assert_eq!(*fetches.lock().unwrap(), 1);
+
+    subscription.set_hints(hints(ConnectionState::Connecting, true, false));
+    sleep(Duration::from_millis(2500)).await;
+    // The first request right away, then once a second.
+    assert_eq!(*fetches.lock().unwrap(), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn pauses_while_hidden_until_refreshed() {
+    let subscription = StatusSubscription::default();
+    subscription.set_hints(hints(ConnectionState::Idle, false, false));
+    let fetches = spawn_watch(&subscription, None);
+
+    sleep(Duration::from_secs(600)).await;
+    assert_eq!(*fetches.lock().unwrap(), 1);
+
+    subscription.set_hints(hints(ConnectionState::Idle, true, false));
+    sleep(Duration::from_millis(10)).await;
+    // Shown again long after the last request.
+    assert_eq!(*fetches.lock().unwrap(), 2);
+
+    subscription.set_hints(hints(ConnectionState::Idle, false, false));
+    subscription.refresh();
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(*fetches.lock().unwrap(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn pushed_changes_fetch_at_once() {
+    let subscription = StatusSubscription::default();
+    let (push, pushes) = unbounded_channel();
+    let fetches = spawn_watch(&subscription, Some(pushes));
+
+    sleep(Duration::from_secs(1)).await;
+    push.send(()).unwrap();
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(*fetches.lock().unwrap(), 2);
+}
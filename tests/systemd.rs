@@ -0,0 +1,58 @@
+#![cfg(all(feature = "systemd", feature = "mock"))]
+
+use std::io::ErrorKind;
+
+use snx_rs_gui::systemd::{FakeSystemd, UnitAction, SNX_RS_UNIT};
+
+#[tokio::test]
+async fn reports_unit_state() {
+    let fake = FakeSystemd::new(SNX_RS_UNIT, true);
+    let service = fake.connect().await.unwrap();
+
+    let state = service.state().await.unwrap();
+    assert!(state.is_installed());
+    assert!(!state.is_active());
+    assert_eq!(state.to_string(), "inactive (dead)");
+    assert_eq!(state.active_since(), None);
+
+    fake.set_active(true);
+    let state = service.state().await.unwrap();
+    assert!(state.is_active());
+    assert_eq!(state.sub_state, "running");
+    assert!(state.active_since().is_some());
+}
+
+#[tokio::test]
+async fn starts_stops_and_restarts_unit() {
+    let fake = FakeSystemd::new(SNX_RS_UNIT, true);
+    let service = fake.connect().await.unwrap();
+
+    service.run(UnitAction::Start).await.unwrap();
+    assert!(fake.is_active());
+    service.run(UnitAction::Stop).await.unwrap();
+    assert!(!fake.is_active());
+    service.run(UnitAction::Restart).await.unwrap();
+    assert!(service.state().await.unwrap().is_active());
+}
+
+#[tokio::test]
+async fn refused_authorization_is_permission_denied() {
+    let fake = FakeSystemd::new(SNX_RS_UNIT, true);
+    fake.set_authorized(false);
+    let service = fake.connect().await.unwrap();
+
+    let e = service.run(UnitAction::Restart).await.unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+    assert!(!fake.is_active());
+}
+
+#[tokio::test]
+async fn missing_unit_is_not_installed() {
+    let fake = FakeSystemd::new(SNX_RS_UNIT, false);
+    let service = fake.connect().await.unwrap();
+
+    let state = service.state().await.unwrap();
+    assert!(!state.is_installed());
+    assert_eq!(state.to_string(), "not installed");
+    assert!(service.run(UnitAction::Start).await.is_err());
+}
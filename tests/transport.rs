@@ -0,0 +1,152 @@
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+use snx_rs_gui::client::{ConnectResponse, TunnelClient};
+use snx_rs_gui::mock::{Behavior, MockServer};
+use snx_rs_gui::model::{
+    ConnectionStatus, TunnelParams, TunnelServiceRequest, TunnelServiceResponse,
+};
+
+/// Answers the first request with `replies`, sent in order as raw datagrams.
+async fn fault_server(replies: Vec<Vec<u8>>) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 65_536];
+        let (_, peer) = socket.recv_from(&mut buf).await.unwrap();
+        for reply in replies {
+            socket.send_to(&reply, peer).await.unwrap();
+        }
+        // Keep the port open, so that requests are not refused.
+        std::future::pending::<()>().await;
+    });
+    address
+}
+
+fn json(response: &TunnelServiceResponse) -> Vec<u8> {
+    serde_json::to_vec(response).unwrap()
+}
+
+#[tokio::test]
+async fn lost_status_requests_are_sent_again() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_status([Behavior::Drop, Behavior::Drop])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    let response = client.get_status().await.unwrap();
+    assert!(matches!(
+        response,
+        TunnelServiceResponse::ConnectionStatus(_)
+    ));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_retries() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_status([Behavior::Drop, Behavior::Drop])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr()).with_retries(1);
+
+    let e = client.get_status().await.unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn connect_is_never_sent_twice() {
+    let server = MockServer::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .on_connect([Behavior::Drop])
+        .spawn()
+        .unwrap();
+    let client = TunnelClient::new(server.local_addr());
+
+    assert!(client.connect(TunnelParams::default()).await.is_err());
+    assert!(matches!(
+        server.requests()[..],
+        [TunnelServiceRequest::Connect(_)]
+    ));
+}
+
+#[tokio::test]
+async fn stale_and_malformed_responses_are_discarded() {
+    let address = fault_server(vec![
+        b"\0\0garbage".to_vec(),
+        json(&TunnelServiceResponse::ConnectionStatus(
+            ConnectionStatus::default(),
+        )),
+        json(&TunnelServiceResponse::Ok),
+    ])
+    .await;
+    let client = TunnelClient::new(address);
+
+    let response = client.connect(TunnelParams::default()).await.unwrap();
+    assert_eq!(response, ConnectResponse::Accepted);
+}
+
+#[tokio::test]
+async fn status_takes_every_response() {
+    for reply in [
+        TunnelServiceResponse::Ok,
+        TunnelServiceResponse::PasswordPrompt("Enter OTP".to_string()),
+        TunnelServiceResponse::IdentityProvider("http://127.0.0.1/login".to_string()),
+    ] {
+        let address = fault_server(vec![json(&reply)]).await;
+        let client = TunnelClient::new(address).with_retries(0);
+
+        let response = client.get_status().await.unwrap();
+        assert_eq!(json(&response), json(&reply));
+    }
+}
+
+#[tokio::test]
+async fn only_malformed_responses_are_invalid_data() {
+    let address = fault_server(vec![b"{\"Unknown\": 1}".to_vec()]).await;
+    let client = TunnelClient::new(address).with_retries(0);
+
+    let e = client.get_status().await.unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn large_responses_fit() {
+    let status = ConnectionStatus {
+        routes: Some(vec!["10.0.0.0/8".to_string(); 2000]),
+        ..ConnectionStatus::default()
+    };
+    let address = fault_server(vec![json(&TunnelServiceResponse::ConnectionStatus(status))]).await;
+    let client = TunnelClient::new(address);
+
+    match client.get_status().await.unwrap() {
+        TunnelServiceResponse::ConnectionStatus(status) => {
+            assert_eq!(status.routes.unwrap().len(), 2000)
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn stopped_service_fails_without_waiting() {
+    let address = UdpSocket::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = TunnelClient::new(address).with_timeout(Duration::from_secs(5));
+
+    let start = Instant::now();
+    let e = client.get_status().await.unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::ConnectionRefused);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
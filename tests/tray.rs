@@ -0,0 +1,35 @@
+use snx_rs_gui::state::ConnectionState;
+use snx_rs_gui::tray::{TrayModel, TrayStatus};
+
+#[test]
+fn tray_status_follows_connection_state() {
+    assert_eq!(
+        TrayStatus::new(&ConnectionState::Connected, true),
+        TrayStatus::Connected
+    );
+    assert_eq!(
+        TrayStatus::new(&ConnectionState::Connecting, false),
+        TrayStatus::Busy
+    );
+    assert_eq!(
+        TrayStatus::new(&ConnectionState::Idle, true),
+        TrayStatus::Disconnected
+    );
+    assert_eq!(
+        TrayStatus::new(&ConnectionState::Failed("x".to_string()), false),
+        TrayStatus::ServiceDown
+    );
+}
+
+#[test]
+fn tray_menu_entries_need_running_service() {
+    let model = TrayModel::new(&ConnectionState::Idle, true, "prod");
+    assert!(model.can_connect && !model.can_disconnect);
+    assert_eq!(model.profile, "prod");
+
+    let model = TrayModel::new(&ConnectionState::Connected, true, "prod");
+    assert!(!model.can_connect && model.can_disconnect);
+
+    let model = TrayModel::new(&ConnectionState::Idle, false, "prod");
+    assert!(!model.can_connect && !model.can_disconnect);
+}
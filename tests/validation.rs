@@ -0,0 +1,269 @@
+use chrono::{Duration, TimeZone, Utc};
+use std::path::{Path, PathBuf};
+
+use snx_rs_gui::model::{LoginType, TunnelParams};
+use snx_rs_gui::pinning::Fingerprint;
+use snx_rs_gui::validation::{
+    check_domain, check_server_name, validate_params, validate_params_at, Field, Severity,
+};
+
+fn certificate(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/certs")
+        .join(name)
+}
+
+fn params() -> TunnelParams {
+    TunnelParams {
+        server_name: "vpn.example.com".to_string(),
+        user_name: "alice".to_string(),
+        password: "secret".to_string(),
+        ..TunnelParams::default()
+    }
+}
+
+fn fields(params: &TunnelParams) -> Vec<(Field, Severity)> {
+    validate_params(params)
+        .issues
+        .iter()
+        .map(|issue| (issue.field, issue.severity))
+        .collect()
+}
+
+#[test]
+fn server_name_syntax() {
+    for valid in [
+        "vpn.example.com",
+        "vpn",
+        "vpn.example.com:443",
+        "192.0.2.1",
+        "192.0.2.1:4433",
+        "2001:db8::1",
+        "[2001:db8::1]:443",
+        "vpn-1.example.com.",
+    ] {
+        assert!(check_server_name(valid).is_ok(), "{}", valid);
+    }
+    for invalid in [
+        "",
+        "vpn example.com",
+        "vpn.example.com:0",
+        "vpn.example.com:https",
+        "-vpn.example.com",
+        "vpn..example.com",
+        "https://vpn.example.com",
+    ] {
+        assert!(check_server_name(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn domain_syntax() {
+    assert!(check_domain("corp.example.com").is_ok());
+    assert!(check_domain("example.com.").is_ok());
+    assert!(check_domain(&format!("{}.com", "a".repeat(63))).is_ok());
+
+    assert!(check_domain(&format!("{}.com", "a".repeat(64))).is_err());
+    assert!(check_domain("under_score.example.com").is_err());
+    assert!(check_domain("example-.com").is_err());
+    assert!(check_domain(".").is_err());
+}
+
+#[test]
+fn complete_params_are_valid() {
+    let validation = validate_params(&params());
+
+    assert!(validation.issues.is_empty(), "{:?}", validation);
+    assert!(!validation.has_errors());
+}
+
+#[test]
+fn login_type_decides_required_credentials() {
+    let empty = TunnelParams {
+        user_name: String::new(),
+        password: String::new(),
+        ..params()
+    };
+
+    assert_eq!(
+        fields(&empty),
+        [
+            (Field::UserName, Severity::Error),
+            (Field::Password, Severity::Error)
+        ]
+    );
+    let sso = TunnelParams {
+        login_type: LoginType::SsoAzure,
+        ..empty
+    };
+    assert!(fields(&sso).is_empty());
+}
+
+#[test]
+fn search_domains_and_conflicting_routes() {
+    let params = TunnelParams {
+        // Configs of older versions hold an empty domain, it is not an error.
+        search_domains: vec![
+            "".to_string(),
+            "example.com".to_string(),
+            "not a domain".to_string(),
+        ],
+        default_route: true,
+        no_routing: true,
+        ..params()
+    };
+
+    let validation = validate_params(&params);
+
+    assert_eq!(
+        fields(&params),
+        [
+            (Field::SearchDomains, Severity::Error),
+            (Field::DefaultRoute, Severity::Error)
+        ]
+    );
+    assert_eq!(
+        validation
+            .field(Field::SearchDomains)
+            .next()
+            .unwrap()
+            .message,
+        "not a domain is not a valid domain name"
+    );
+}
+
+#[test]
+fn ca_cert_must_be_readable() {
+    let dir = tempfile::tempdir().unwrap();
+    let cert = certificate("corp-root-ca.pem");
+
+    let with_cert = |path: PathBuf| TunnelParams {
+        ca_cert: vec![path],
+        ..params()
+    };
+
+    assert!(fields(&with_cert(cert.clone())).is_empty());
+    assert!(fields(&with_cert(PathBuf::new())).is_empty());
+    assert_eq!(
+        fields(&with_cert(dir.path().join("missing.pem"))),
+        [(Field::CaCert, Severity::Error)]
+    );
+    assert_eq!(
+        fields(&with_cert(dir.path().to_path_buf())),
+        [(Field::CaCert, Severity::Error)]
+    );
+    assert_eq!(
+        fields(&with_cert(certificate("not-a-cert.pem"))),
+        [(Field::CaCert, Severity::Error)]
+    );
+
+    let ignored = TunnelParams {
+        no_cert_check: true,
+        ..with_cert(cert)
+    };
+    let validation = validate_params(&ignored);
+    assert_eq!(
+        fields(&ignored),
+        [
+            (Field::NoCertCheck, Severity::Warning),
+            (Field::NoCertCheck, Severity::Warning)
+        ]
+    );
+    assert!(!validation.has_errors());
+}
+
+#[test]
+fn suggests_pinning_over_no_cert_check() {
+    let unchecked = TunnelParams {
+        no_cert_check: true,
+        ..params()
+    };
+    assert_eq!(
+        fields(&unchecked),
+        [(Field::NoCertCheck, Severity::Warning)]
+    );
+
+    let pinned = TunnelParams {
+        pinned_fingerprints: vec![Fingerprint::of(b"gateway certificate")],
+        ..unchecked
+    };
+    assert!(fields(&pinned).is_empty());
+}
+
+#[test]
+fn warns_about_expiring_ca_certs() {
+    let params = TunnelParams {
+        ca_cert: vec![
+            certificate("corp-bundle.pem"),
+            certificate("corp-old-ca.der"),
+        ],
+        ..params()
+    };
+    let now = Utc.with_ymd_and_hms(2044, 1, 1, 0, 0, 0).unwrap() - Duration::days(10);
+
+    let validation = validate_params_at(&params, now);
+
+    let messages: Vec<String> = validation
+        .field(Field::CaCert)
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "C=CZ, O=Example Corp, OU=IT, CN=Example Corp Root CA expires in 10 days",
+            "O=Example Corp, CN=Example Corp Old CA expired on 2020-01-01",
+        ]
+    );
+    assert!(!validation.has_errors());
+}
+
+#[test]
+fn search_domain_list_editing() {
+    let mut params = params();
+    assert!(params.search_domains.is_empty());
+
+    params.add_search_domains("example.com").unwrap();
+    params
+        .add_search_domains(" corp.example.com, EXAMPLE.com. lab.example.com ")
+        .unwrap();
+    assert_eq!(
+        params.search_domains,
+        ["example.com", "corp.example.com", "lab.example.com"]
+    );
+
+    let e = params
+        .add_search_domains("dev.example.com not_a_domain")
+        .unwrap_err();
+    assert_eq!(e, "not_a_domain is not a valid domain name");
+    assert_eq!(params.search_domains.len(), 3);
+
+    params.move_search_domain(2, 0);
+    params.move_search_domain(1, 2);
+    params.move_search_domain(2, 3);
+    assert_eq!(
+        params.search_domains,
+        ["lab.example.com", "corp.example.com", "example.com"]
+    );
+    assert!(fields(&params).is_empty());
+}
+
+#[test]
+fn search_domains_are_deduplicated_when_loaded() {
+    let loaded: TunnelParams = serde_json::from_str(
+        r#"{"search_domains": ["", " example.com", "Example.com.", "corp.example.com"]}"#,
+    )
+    .unwrap();
+    assert_eq!(loaded.search_domains, ["example.com", "corp.example.com"]);
+
+    let loaded: TunnelParams = serde_json::from_str(r#"{"search_domains": null}"#).unwrap();
+    assert!(loaded.search_domains.is_empty());
+
+    let repeated = TunnelParams {
+        search_domains: vec!["example.com".to_string(), "EXAMPLE.COM".to_string()],
+        ..params()
+    };
+    assert_eq!(
+        fields(&repeated),
+        [(Field::SearchDomains, Severity::Warning)]
+    );
+}